@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 use syewreal::{
     components::QueryWithState,
@@ -87,6 +89,7 @@ fn home(_: &()) -> Html {
                         img: None
                     },
                 )
+                .timeout(Duration::from_secs(5))
                 .append_to(state.clone());
             },
             state,
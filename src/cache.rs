@@ -0,0 +1,141 @@
+use std::rc::Rc;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{hooks::SurrealToken, props::selector::Selector};
+
+/// A key-value store the `QueryCache` persists query results in
+pub trait CacheStorage {
+    fn get(&self, key: &str) -> Option<String>;
+    fn set(&self, key: &str, value: &str);
+    fn remove(&self, key: &str);
+    /// Remove every stored result
+    fn clear(&self);
+}
+
+/// Persists query results in `window.localStorage`
+pub struct LocalStorageCache {
+    prefix: String,
+}
+
+impl LocalStorageCache {
+    /// All keys are prefixed with `prefix` to avoid collisions with other data in the storage
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    /// There is no `localStorage` outside of the browser
+    #[cfg(not(target_arch = "wasm32"))]
+    fn storage() -> Option<web_sys::Storage> {
+        None
+    }
+}
+
+impl CacheStorage for LocalStorageCache {
+    fn get(&self, key: &str) -> Option<String> {
+        Self::storage()?.get_item(&(self.prefix.clone() + key)).ok()?
+    }
+
+    fn set(&self, key: &str, value: &str) {
+        if let Some(storage) = Self::storage() {
+            let _ = storage.set_item(&(self.prefix.clone() + key), value);
+        }
+    }
+
+    fn remove(&self, key: &str) {
+        if let Some(storage) = Self::storage() {
+            let _ = storage.remove_item(&(self.prefix.clone() + key));
+        }
+    }
+
+    fn clear(&self) {
+        if let Some(storage) = Self::storage() {
+            let length = storage.length().unwrap_or_default();
+            let keys = (0..length)
+                .filter_map(|index| storage.key(index).ok()?)
+                .filter(|key| key.starts_with(&self.prefix))
+                .collect::<Vec<_>>();
+            for key in keys {
+                let _ = storage.remove_item(&key);
+            }
+        }
+    }
+}
+
+/// Persists the results of queries so they can be shown immediately on the next start
+#[derive(Clone)]
+pub struct QueryCache(Rc<dyn CacheStorage>);
+
+impl PartialEq for QueryCache {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl QueryCache {
+    pub fn new(storage: impl CacheStorage + 'static) -> Self {
+        Self(Rc::new(storage))
+    }
+
+    /// A cache stored in `window.localStorage`, all keys are prefixed with `prefix`
+    pub fn local_storage(prefix: impl Into<String>) -> Self {
+        Self::new(LocalStorageCache::new(prefix))
+    }
+
+    /// The key a query is stored under
+    ///
+    /// Includes the namespace, the database, who the token is signed in as and the bound params,
+    /// so switching them does not mix up results. `None` (nothing is cached) until the token signed in
+    pub(crate) fn key(token: &SurrealToken, selector: &Selector) -> Option<String> {
+        let auth = (*token.auth).clone()?;
+        let identity = match (auth.get("id"), &auth.user) {
+            (Some(record), _) => record.to_string(),
+            (None, Some(user)) => user.clone(),
+            (None, None) => String::new(),
+        };
+        selector.base.as_ref().map(|statement| {
+            format!(
+                "{}/{}/{:?}:{}/{}/{}",
+                auth.namespace.unwrap_or_default(),
+                auth.database.unwrap_or_default(),
+                auth.level,
+                identity,
+                statement,
+                selector.bindings.json()
+            )
+        })
+    }
+
+    pub(crate) fn load<T: DeserializeOwned>(&self, key: &str) -> Option<Vec<T>> {
+        serde_json::from_str(&self.0.get(key)?).ok()
+    }
+
+    pub(crate) fn store<T: Serialize>(&self, key: &str, data: &[T]) {
+        if let Ok(raw) = serde_json::to_string(data) {
+            self.0.set(key, &raw);
+        }
+    }
+
+    /// Forget the stored result of `selector`
+    pub fn invalidate(&self, token: &SurrealToken, selector: &Selector) {
+        if let Some(key) = Self::key(token, selector) {
+            self.remove(&key);
+        }
+    }
+
+    /// Forget every stored result, e.g. after the user signed out
+    pub fn clear(&self) {
+        self.0.clear();
+    }
+
+    pub(crate) fn remove(&self, key: &str) {
+        self.0.remove(key);
+    }
+}
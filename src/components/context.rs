@@ -1,6 +1,7 @@
-use yew::{Properties, Html, Children, function_component, ContextProvider, Suspense, html};
+use yew::{Properties, Html, Children, function_component, ContextProvider, Suspense, html, use_effect_with_deps, Callback};
+use yew::functional::use_prepared_state;
 
-use crate::hooks::SurrealToken;
+use crate::{config::{ConnectionConfig, RetryPolicy}, hooks::SurrealToken, metrics::QueryMetrics, migrations::MigrationState, offline::OfflineQueue, traffic::TrafficLog};
 
 
 
@@ -8,6 +9,21 @@ use crate::hooks::SurrealToken;
 pub struct SurrealContextProps {
     pub token: SurrealToken,
     pub fallback: Option<Html>,
+    /// Overrides the config of the token for everything inside this context
+    ///
+    /// The token is usually connected before the context is rendered, so only the `timeout` applies right away.
+    /// The other settings are used once the token connects again from inside the context, e.g. with `SurrealToken::sign_in`
+    pub config: Option<ConnectionConfig>,
+    /// The retry policy for every request inside this context
+    pub retry: Option<RetryPolicy>,
+    /// Queues mutations while the database can't be reached, they are replayed when the browser comes back online
+    pub offline: Option<OfflineQueue>,
+    /// Logs every request, see `syewreal::traffic`
+    pub traffic: Option<TrafficLog>,
+    /// Measures every request, see `syewreal::metrics`
+    pub metrics: Option<QueryMetrics>,
+    /// Drawn instead of the `fallback` while the migrations of the token run or after one of them failed
+    pub migrating: Option<Callback<MigrationState, Html>>,
     pub children: Children,
 }
 
@@ -15,15 +31,81 @@ pub struct SurrealContextProps {
 pub fn surreal_context(props: &SurrealContextProps) -> Html {
     let fallback = props.fallback.clone().unwrap_or(html!());
 
-    if *props.token.ready {
+    let mut context = props.token.clone();
+    if let Some(config) = &props.config {
+        context.config = config.clone();
+    }
+    if let Some(retry) = &props.retry {
+        context.retry = retry.clone();
+    }
+    if let Some(offline) = &props.offline {
+        context.offline = Some(offline.clone());
+    }
+    if let Some(traffic) = &props.traffic {
+        context.traffic = Some(traffic.clone());
+    }
+    if let Some(metrics) = &props.metrics {
+        context.metrics = Some(metrics.clone());
+    }
+
+    {
+        let token = context.clone();
+        use_effect_with_deps(
+            move |(ready, queue)| {
+                let listener = match (ready, queue) {
+                    (true, Some(queue)) => {
+                        // mutations may be left over from the last session
+                        queue.replay(&token);
+                        online_listener(queue.clone(), token)
+                    }
+                    _ => None,
+                };
+                move || drop(listener)
+            },
+            (*props.token.ready, context.offline.clone()),
+        );
+    }
+
+    // only the server prepares this flag, so it is only set while hydrating a page rendered on the server.
+    // The children are drawn right away in that case, their queries wait for the token anyway
+    let hydrating = use_prepared_state!(|_| -> bool { true }, ())
+        .ok()
+        .flatten()
+        .map_or(false, |hydrating| *hydrating);
+    let migrating = matches!(
+        &*props.token.migration_state,
+        MigrationState::Running { .. } | MigrationState::Failed { .. }
+    );
+
+    if *props.token.ready || (hydrating && !migrating) {
         html! {
             <Suspense {fallback}>
-                <ContextProvider<SurrealToken> context={props.token.clone()}>
+                <ContextProvider<SurrealToken> {context}>
                     { for props.children.iter() }
                 </ContextProvider<SurrealToken>>
             </Suspense>
         }
     } else {
-        fallback
+        match (&props.migrating, &*props.token.migration_state) {
+            (Some(migrating), state @ (MigrationState::Running { .. } | MigrationState::Failed { .. })) => {
+                migrating.emit(state.clone())
+            }
+            _ => fallback,
+        }
     }
-}
\ No newline at end of file
+}
+
+/// Replays the queue whenever the browser comes back online
+#[cfg(target_arch = "wasm32")]
+fn online_listener(queue: OfflineQueue, token: SurrealToken) -> Option<gloo_events::EventListener> {
+    let window = web_sys::window()?;
+    Some(gloo_events::EventListener::new(&window, "online", move |_| {
+        queue.replay(&token);
+    }))
+}
+
+/// Native targets have no `online` event, the queue is only replayed when the token connects
+#[cfg(not(target_arch = "wasm32"))]
+fn online_listener(_queue: OfflineQueue, _token: SurrealToken) -> Option<()> {
+    None
+}
@@ -0,0 +1,101 @@
+use yew::{
+    function_component, hook, html, use_effect_with_deps, use_mut_ref, AttrValue, Callback, Children, Html,
+    HtmlResult, Properties,
+    suspense::{Suspension, SuspensionHandle, SuspensionResult},
+};
+
+use crate::hooks::{use_auth, use_surreal, AuthInfo, AuthLevel};
+
+#[derive(Properties, PartialEq)]
+pub struct RequireAuthProps {
+    /// Only admit users of at least this level, e.g. `Database` admits database, namespace and root users
+    pub level: Option<AuthLevel>,
+    /// Only admit users of this scope
+    pub scope: Option<AttrValue>,
+    /// Rendered instead of the children if the user is not admitted
+    pub fallback: Option<Html>,
+    /// Called when the user is not admitted, e.g. to redirect to a login page
+    pub on_denied: Option<Callback<()>>,
+    pub children: Children,
+}
+
+/// Only renders its children if the current `SurrealToken` is signed in (with at least the given level or the given scope)
+#[function_component(RequireAuth)]
+pub fn require_auth(props: &RequireAuthProps) -> HtmlResult {
+    use_ready()?;
+    let allowed = match use_auth() {
+        Some(auth) => {
+            props.level.map_or(true, |level| auth.level() >= level)
+                && props
+                    .scope
+                    .as_ref()
+                    .map_or(true, |scope| auth.scope.as_deref() == Some(scope.as_str()))
+        }
+        None => false,
+    };
+
+    use_guard(allowed, props.on_denied.clone());
+
+    Ok(if allowed {
+        html!(<>{ for props.children.iter() }</>)
+    } else {
+        props.fallback.clone().unwrap_or_default()
+    })
+}
+
+#[derive(Properties, PartialEq)]
+pub struct RequirePermissionProps {
+    /// Decides whether the signed in user is admitted
+    pub check: Callback<AuthInfo, bool>,
+    /// Rendered instead of the children if the user is not admitted
+    pub fallback: Option<Html>,
+    /// Called when the user is not admitted, e.g. to redirect to a login page
+    pub on_denied: Option<Callback<()>>,
+    pub children: Children,
+}
+
+/// Only renders its children if `check` admits the user the current `SurrealToken` is signed in as
+#[function_component(RequirePermission)]
+pub fn require_permission(props: &RequirePermissionProps) -> HtmlResult {
+    use_ready()?;
+    let allowed = use_auth().map_or(false, |auth| props.check.emit(auth));
+
+    use_guard(allowed, props.on_denied.clone());
+
+    Ok(if allowed {
+        html!(<>{ for props.children.iter() }</>)
+    } else {
+        props.fallback.clone().unwrap_or_default()
+    })
+}
+
+/// Suspends until the token signed in, who the user is is not known before.
+/// While hydrating a page rendered on the server the children of `<SurrealContext/>` are drawn before that
+#[hook]
+fn use_ready() -> SuspensionResult<()> {
+    let ready = *use_surreal().ready;
+    let suspension = use_mut_ref(|| None::<(Suspension, SuspensionHandle)>);
+    let mut suspension = suspension.borrow_mut();
+    if ready {
+        // dropping the handle resumes the suspension
+        suspension.take();
+        return Ok(());
+    }
+    let (suspension, _) = suspension.get_or_insert_with(Suspension::new);
+    Err(suspension.clone())
+}
+
+/// Calls `on_denied` whenever access gets denied
+#[hook]
+fn use_guard(allowed: bool, on_denied: Option<Callback<()>>) {
+    use_effect_with_deps(
+        move |allowed| {
+            if !*allowed {
+                if let Some(on_denied) = on_denied {
+                    on_denied.emit(());
+                }
+            }
+        },
+        allowed,
+    );
+}
@@ -0,0 +1,274 @@
+use std::rc::Rc;
+
+use surrealdb::opt::auth::{Database, Root, Scope};
+use web_sys::HtmlInputElement;
+use yew::{
+    function_component, html, use_reducer_eq, use_state_eq, AttrValue, Callback, Children, Html,
+    InputEvent, Properties, Reducible, SubmitEvent, TargetCast,
+};
+
+use crate::components::SurrealContext;
+use crate::config::ConnectionConfig;
+use crate::hooks::{use_surreal_token_with_config, SignInError, SignInState, SurrealToken};
+
+/// Which kind of user the `<SurrealLogin/>` form signs in
+#[derive(Clone, PartialEq, Debug)]
+pub enum LoginMode {
+    Root,
+    Database {
+        namespace: AttrValue,
+        database: AttrValue,
+    },
+    /// Signs in to a scope, the username and password are passed as the `$username` and `$password` params
+    Scope {
+        namespace: AttrValue,
+        database: AttrValue,
+        scope: AttrValue,
+    },
+}
+
+impl LoginMode {
+    async fn sign_in(
+        &self,
+        token: &SurrealToken,
+        url: String,
+        input: &LoginInput,
+    ) -> Result<(), SignInError> {
+        let username = input.username.as_str();
+        let password = input.password.as_str();
+        match self {
+            LoginMode::Root => {
+                token
+                    .connect_and_sign_in(url, Root { username, password })
+                    .await
+            }
+            LoginMode::Database {
+                namespace,
+                database,
+            } => {
+                token
+                    .connect_and_sign_in(
+                        url,
+                        Database {
+                            namespace: namespace.as_str(),
+                            database: database.as_str(),
+                            username,
+                            password,
+                        },
+                    )
+                    .await
+            }
+            LoginMode::Scope {
+                namespace,
+                database,
+                scope,
+            } => {
+                token
+                    .connect_and_sign_in(
+                        url,
+                        Scope {
+                            namespace: namespace.as_str(),
+                            database: database.as_str(),
+                            scope: scope.as_str(),
+                            params: serde_json::json!({
+                                "username": username,
+                                "password": password,
+                            }),
+                        },
+                    )
+                    .await
+            }
+        }
+    }
+}
+
+/// The values entered into a `<SurrealLogin/>` form
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct LoginInput {
+    pub username: AttrValue,
+    pub password: AttrValue,
+}
+
+/// A change to a single field of the `LoginInput`
+///
+/// Changes are applied to the latest input, so a password manager filling both fields at once keeps both values
+pub enum LoginField {
+    Username(AttrValue),
+    Password(AttrValue),
+}
+
+impl Reducible for LoginInput {
+    type Action = LoginField;
+
+    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
+        let mut input = (*self).clone();
+        match action {
+            LoginField::Username(username) => input.username = username,
+            LoginField::Password(password) => input.password = password,
+        }
+        Rc::new(input)
+    }
+}
+
+/// Everything a custom `render` callback of `<SurrealLogin/>` needs to draw the form
+#[derive(Clone, PartialEq)]
+pub struct LoginFormContext {
+    pub mode: LoginMode,
+    pub input: LoginInput,
+    pub state: SignInState,
+    pub validation_error: Option<AttrValue>,
+    pub on_username: Callback<String>,
+    pub on_password: Callback<String>,
+    pub on_submit: Callback<()>,
+}
+
+impl LoginFormContext {
+    pub fn is_loading(&self) -> bool {
+        self.state == SignInState::Loading
+    }
+
+    /// The validation error or the reason the last sign in failed
+    pub fn error(&self) -> Option<AttrValue> {
+        match (&self.validation_error, &self.state) {
+            (Some(error), _) => Some(error.clone()),
+            (None, SignInState::Failed(error)) => Some(error.to_string().into()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct SurrealLoginProps {
+    pub url: AttrValue,
+    pub mode: LoginMode,
+    /// Draws the form instead of the default one
+    pub render: Option<Callback<LoginFormContext, Html>>,
+    /// Checks the input before signing in, defaults to requiring a username and a password
+    pub validate: Option<Callback<LoginInput, Result<(), AttrValue>>>,
+    /// Called with the connected token after a successful sign in
+    pub on_login: Option<Callback<SurrealToken>>,
+    /// Passed on to the `<SurrealContext/>` that wraps the children
+    pub fallback: Option<Html>,
+    /// How to connect to the database
+    pub config: Option<ConnectionConfig>,
+    pub children: Children,
+}
+
+/// Shows a login form until the user signed in, then renders its children inside a `<SurrealContext/>`
+#[function_component(SurrealLogin)]
+pub fn surreal_login(props: &SurrealLoginProps) -> Html {
+    let token = use_surreal_token_with_config(props.config.clone().unwrap_or_default());
+    let input = use_reducer_eq(LoginInput::default);
+    let state = use_state_eq(|| SignInState::Idle);
+    let validation_error = use_state_eq(|| None::<AttrValue>);
+
+    if *state == SignInState::Success {
+        return html! {
+            <SurrealContext token={token} fallback={props.fallback.clone()}>
+                { for props.children.iter() }
+            </SurrealContext>
+        };
+    }
+
+    let on_username = {
+        let input = input.clone();
+        Callback::from(move |username: String| input.dispatch(LoginField::Username(username.into())))
+    };
+
+    let on_password = {
+        let input = input.clone();
+        Callback::from(move |password: String| input.dispatch(LoginField::Password(password.into())))
+    };
+
+    let on_submit = {
+        let token = token.clone();
+        let input = input.clone();
+        let state = state.clone();
+        let validation_error = validation_error.clone();
+        let url = props.url.clone();
+        let mode = props.mode.clone();
+        let validate = props.validate.clone();
+        let on_login = props.on_login.clone();
+        Callback::from(move |_| {
+            if *state == SignInState::Loading {
+                return;
+            }
+
+            let input = (*input).clone();
+            let validation = match &validate {
+                Some(validate) => validate.emit(input.clone()),
+                None => require_credentials(&input),
+            };
+            if let Err(error) = validation {
+                validation_error.set(Some(error));
+                return;
+            }
+            validation_error.set(None);
+            state.set(SignInState::Loading);
+
+            let token = token.clone();
+            let state = state.clone();
+            let url = url.to_string();
+            let mode = mode.clone();
+            let on_login = on_login.clone();
+            yew::platform::spawn_local(async move {
+                match mode.sign_in(&token, url, &input).await {
+                    Ok(()) => {
+                        state.set(SignInState::Success);
+                        if let Some(on_login) = on_login {
+                            on_login.emit(token);
+                        }
+                    }
+                    Err(error) => state.set(SignInState::Failed(error)),
+                }
+            });
+        })
+    };
+
+    let context = LoginFormContext {
+        mode: props.mode.clone(),
+        input: (*input).clone(),
+        state: (*state).clone(),
+        validation_error: (*validation_error).clone(),
+        on_username,
+        on_password,
+        on_submit,
+    };
+
+    match &props.render {
+        Some(render) => render.emit(context),
+        None => default_login_form(context),
+    }
+}
+
+fn require_credentials(input: &LoginInput) -> Result<(), AttrValue> {
+    if input.username.is_empty() || input.password.is_empty() {
+        Err("Username and password are required".into())
+    } else {
+        Ok(())
+    }
+}
+
+fn default_login_form(context: LoginFormContext) -> Html {
+    let loading = context.is_loading();
+    let onsubmit = context.on_submit.reform(|event: SubmitEvent| event.prevent_default());
+    let on_username = context
+        .on_username
+        .reform(|event: InputEvent| event.target_unchecked_into::<HtmlInputElement>().value());
+    let on_password = context
+        .on_password
+        .reform(|event: InputEvent| event.target_unchecked_into::<HtmlInputElement>().value());
+
+    html! {
+        <form class="surreal-login" {onsubmit}>
+            <input type="text" name="username" placeholder="Username" autocomplete="username"
+                value={context.input.username.clone()} oninput={on_username} disabled={loading}/>
+            <input type="password" name="password" placeholder="Password" autocomplete="current-password"
+                value={context.input.password.clone()} oninput={on_password} disabled={loading}/>
+            if let Some(error) = context.error() {
+                <div class="error">{error}</div>
+            }
+            <button type="submit" disabled={loading}>{if loading {"Signing in..."} else {"Sign in"}}</button>
+        </form>
+    }
+}
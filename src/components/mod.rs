@@ -1,4 +1,8 @@
 mod query;
 mod context;
+mod guard;
+mod login;
 pub use query::*;
-pub use context::*;
\ No newline at end of file
+pub use context::*;
+pub use guard::*;
+pub use login::*;
\ No newline at end of file
@@ -5,7 +5,7 @@ use yew::{
 };
 
 use crate::{
-    hooks::{use_query_state, SurrealSelfRef},
+    hooks::{use_prepared_query_state, QueryOptions, SurrealSelfRef},
     props::{
         id::HasID,
         surreal_props::{PropsNoState, PropsWithState, SurrealProps},
@@ -13,7 +13,7 @@ use crate::{
 };
 
 #[function_component]
-pub fn Query<Inner>(props: &<<Inner as BaseComponent>::Properties as SurrealProps>::Local) -> Html
+pub fn Query<Inner>(props: &<<Inner as BaseComponent>::Properties as SurrealProps>::Local) -> HtmlResult
 where
     Inner: BaseComponent,
     <Inner as BaseComponent>::Properties: SurrealProps + Clone + HasID,
@@ -22,12 +22,12 @@ where
     <<Inner as BaseComponent>::Properties as SurrealProps>::Local: Properties + Clone,
     <<Inner as BaseComponent>::Properties as SurrealProps>::LocalWithState: Properties + Clone,
 {
-    let state = use_query_state::<Inner::Properties>(props.get_selector());
+    let state = use_prepared_query_state::<Inner::Properties>(props.get_selector(), QueryOptions::default())?;
     let props_with_state = props.with_state(state);
 
-    html!(
+    Ok(html!(
         <QueryWithState<Inner>  ..props_with_state/>
-    )
+    ))
 }
 
 /// perform the specified query and try to deserialize the answer as Inner::Properties
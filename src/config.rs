@@ -0,0 +1,152 @@
+use std::time::Duration;
+
+use crate::error::{ErrorKind, SurrealError};
+
+/// How a `SurrealToken` connects to the database
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectionConfig {
+    /// Number of requests that can be queued on the connection, 0 means unbounded
+    pub capacity: usize,
+    /// Connect via `wss://` instead of `ws://` if the url has no scheme, needed behind TLS terminating proxies
+    pub secure: bool,
+    /// Connect via `http://`/`https://` instead of a websocket if the url has no scheme, needs the `protocol-http` feature
+    pub http: bool,
+    /// Connect in strict mode, namespaces, databases and tables have to be defined before they are used.
+    /// Only embedded databases (`mem://`, `indxdb://`) support it
+    pub strict: bool,
+    /// How long to wait for the database before giving up, `None` waits forever
+    pub timeout: Option<Duration>,
+}
+
+impl Default for ConnectionConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 100000,
+            secure: false,
+            http: false,
+            strict: false,
+            timeout: None,
+        }
+    }
+}
+
+impl ConnectionConfig {
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    pub fn secure(mut self) -> Self {
+        self.secure = true;
+        self
+    }
+
+    pub fn http(mut self) -> Self {
+        self.http = true;
+        self
+    }
+
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The address to connect to, urls without a scheme are connected to via websocket unless `http` is set
+    pub(crate) fn endpoint(&self, url: String) -> String {
+        if url.contains("://") {
+            return url;
+        }
+        let scheme = match (self.http, self.secure) {
+            (true, true) => "https",
+            (true, false) => "http",
+            (false, true) => "wss",
+            (false, false) => "ws",
+        };
+        format!("{}://{}", scheme, url)
+    }
+}
+
+/// How often and when a failed request is sent again
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// How often a request is sent at most, 1 disables retries
+    pub max_attempts: u32,
+    /// How long to wait before the first retry
+    pub backoff: Duration,
+    /// The wait time is multiplied by this after every retry
+    pub backoff_factor: u32,
+    /// Which errors are worth another attempt
+    pub retry_on: Vec<ErrorKind>,
+    /// Whether creates, updates and deletes are retried as well, they may have been applied even though the request failed
+    pub mutations: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff: Duration::from_millis(500),
+            backoff_factor: 2,
+            retry_on: vec![ErrorKind::Connection, ErrorKind::TimedOut],
+            mutations: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never send a request twice
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Send a request up to `max_attempts` times if it fails because of the connection or a timeout
+    pub fn attempts(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_backoff(mut self, backoff: Duration, backoff_factor: u32) -> Self {
+        self.backoff = backoff;
+        self.backoff_factor = backoff_factor;
+        self
+    }
+
+    pub fn retry_on(mut self, kinds: impl IntoIterator<Item = ErrorKind>) -> Self {
+        self.retry_on = kinds.into_iter().collect();
+        self
+    }
+
+    /// Retry creates, updates and deletes as well, only use this if sending them twice does no harm
+    pub fn with_mutations(mut self) -> Self {
+        self.mutations = true;
+        self
+    }
+
+    /// The policy for a create, update or delete, these are only sent once unless `mutations` is set
+    pub(crate) fn for_mutation(&self) -> Self {
+        match self.mutations {
+            true => self.clone(),
+            false => Self {
+                max_attempts: 1,
+                ..self.clone()
+            },
+        }
+    }
+
+    pub(crate) fn should_retry(&self, attempt: u32, error: &SurrealError) -> bool {
+        attempt < self.max_attempts && self.retry_on.contains(&error.kind())
+    }
+
+    /// How long to wait after the `attempt`th attempt failed
+    pub(crate) fn delay(&self, attempt: u32) -> Duration {
+        self.backoff
+            .saturating_mul(self.backoff_factor.saturating_pow(attempt.saturating_sub(1)))
+    }
+}
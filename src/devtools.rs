@@ -0,0 +1,233 @@
+//! An in-app panel that shows what syewreal is doing, enabled by the `devtools` feature
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, VecDeque},
+    rc::Rc,
+};
+
+use serde_json::Value;
+use yew::{
+    function_component, hook, html, use_effect, use_effect_with_deps, use_force_update, use_memo,
+    use_mut_ref, Callback, Html, MouseEvent, Properties,
+};
+
+use crate::{
+    hooks::use_surreal,
+    traffic::{TrafficEvent, TrafficLogger, TrafficPhase},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryStatus {
+    Loading,
+    Ok,
+    /// The last request failed, the state still holds the old data or is suspended
+    Error(String),
+}
+
+/// What the devtools know about a mounted `QueryState`
+#[derive(Clone)]
+pub struct QueryInfo {
+    pub selector: String,
+    /// The values bound to the `$params` of the selector
+    pub parameters: Value,
+    pub status: QueryStatus,
+    pub count: usize,
+    /// Serializes the records, only called while the panel is drawn
+    pub records: Rc<dyn Fn() -> Vec<Value>>,
+    /// Where the records are kept, every change of the data moves them.
+    /// `records` keeps them alive, so a new address always means new data
+    pub(crate) data: *const (),
+    /// Query again and keep showing the current data meanwhile
+    pub refetch: Callback<()>,
+    /// Drop the local and persisted data and query again
+    pub invalidate: Callback<()>,
+}
+
+impl QueryInfo {
+    // the callbacks are recreated on every render and don't tell anything about the query
+    fn same_as(&self, other: &Self) -> bool {
+        self.selector == other.selector
+            && self.parameters == other.parameters
+            && self.status == other.status
+            && self.data == other.data
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    queries: RefCell<BTreeMap<u64, QueryInfo>>,
+    listeners: RefCell<Vec<(u64, Callback<()>)>>,
+    next_id: Cell<u64>,
+}
+
+impl Registry {
+    fn next_id(&self) -> u64 {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        id
+    }
+
+    fn notify(&self) {
+        let listeners = self.listeners.borrow().clone();
+        for (_, listener) in listeners {
+            listener.emit(());
+        }
+    }
+}
+
+thread_local! {
+    static REGISTRY: Registry = Registry::default();
+}
+
+/// Show a `QueryState` in the devtools while the calling component is mounted
+#[hook]
+pub(crate) fn use_devtools_entry(info: QueryInfo) {
+    let id = *use_memo(|_| REGISTRY.with(Registry::next_id), ());
+
+    use_effect_with_deps(
+        move |_| {
+            move || {
+                REGISTRY.with(|registry| {
+                    registry.queries.borrow_mut().remove(&id);
+                    registry.notify();
+                })
+            }
+        },
+        (),
+    );
+
+    use_effect(move || {
+        REGISTRY.with(|registry| {
+            let changed = {
+                let mut queries = registry.queries.borrow_mut();
+                let changed = !queries.get(&id).map(|old| old.same_as(&info)).unwrap_or(false);
+                queries.insert(id, info);
+                changed
+            };
+            if changed {
+                registry.notify();
+            }
+        });
+    });
+}
+
+#[derive(Properties, PartialEq)]
+pub struct SyewrealDevtoolsProps {
+    /// How many traffic events are shown, defaults to 50
+    pub traffic_limit: Option<usize>,
+}
+
+/// Lists the active queries with their status and data and the recent traffic of the surrounding `<SurrealContext/>`
+///
+/// The traffic is only shown if the context has a `TrafficLog`
+#[function_component(SyewrealDevtools)]
+pub fn syewreal_devtools(props: &SyewrealDevtoolsProps) -> Html {
+    let token = use_surreal();
+    let update = use_force_update();
+    let traffic = use_mut_ref(VecDeque::<TrafficEvent>::new);
+    let limit = props.traffic_limit.unwrap_or(50);
+
+    {
+        let update = update.clone();
+        use_effect_with_deps(
+            move |_| {
+                let id = REGISTRY.with(|registry| {
+                    let id = registry.next_id();
+                    registry
+                        .listeners
+                        .borrow_mut()
+                        .push((id, Callback::from(move |_| update.force_update())));
+                    id
+                });
+                move || {
+                    REGISTRY.with(|registry| {
+                        registry.listeners.borrow_mut().retain(|(other, _)| *other != id)
+                    })
+                }
+            },
+            (),
+        );
+    }
+
+    {
+        let traffic = traffic.clone();
+        use_effect_with_deps(
+            move |log| {
+                let sink = log.clone().map(|log| {
+                    let sink: Rc<dyn TrafficLogger> = Rc::new(Callback::from(move |event: TrafficEvent| {
+                        let mut traffic = traffic.borrow_mut();
+                        traffic.push_front(event);
+                        traffic.truncate(limit);
+                        update.force_update();
+                    }));
+                    log.add_shared(sink.clone());
+                    (log, sink)
+                });
+                move || {
+                    if let Some((log, sink)) = sink {
+                        log.remove_shared(&sink);
+                    }
+                }
+            },
+            token.traffic.clone(),
+        );
+    }
+
+    let queries = REGISTRY.with(|registry| registry.queries.borrow().values().cloned().collect::<Vec<_>>());
+    let traffic = traffic.borrow().iter().map(traffic_row).collect::<Html>();
+
+    html! {
+        <div class="syewreal-devtools">
+            <h4>{format!("Queries ({})", queries.len())}</h4>
+            <table>
+                <tr><th>{"Selector"}</th><th>{"Parameters"}</th><th>{"Status"}</th><th>{"Records"}</th><th></th></tr>
+                { for queries.into_iter().map(query_row) }
+            </table>
+            if token.traffic.is_some() {
+                <h4>{"Traffic"}</h4>
+                <ul>{traffic}</ul>
+            }
+        </div>
+    }
+}
+
+fn query_row(info: QueryInfo) -> Html {
+    let status = match &info.status {
+        QueryStatus::Loading => "loading".to_owned(),
+        QueryStatus::Ok => "ok".to_owned(),
+        QueryStatus::Error(error) => format!("error: {}", error),
+    };
+    let records = serde_json::to_string_pretty(&(info.records)()).unwrap_or_default();
+    let parameters = match &info.parameters {
+        Value::Null => String::new(),
+        parameters => parameters.to_string(),
+    };
+    html! {
+        <tr>
+            <td><code>{info.selector.clone()}</code></td>
+            <td><code>{parameters}</code></td>
+            <td>{status}</td>
+            <td>
+                <details>
+                    <summary>{info.count}</summary>
+                    <pre>{records}</pre>
+                </details>
+            </td>
+            <td>
+                <button onclick={info.refetch.reform(|_: MouseEvent| ())}>{"Refetch"}</button>
+                <button onclick={info.invalidate.reform(|_: MouseEvent| ())}>{"Invalidate"}</button>
+            </td>
+        </tr>
+    }
+}
+
+fn traffic_row(event: &TrafficEvent) -> Html {
+    let phase = match &event.phase {
+        TrafficPhase::Sent { .. } => "sent".to_owned(),
+        TrafficPhase::Received { elapsed } => format!("received after {:?}", elapsed),
+        TrafficPhase::Failed { elapsed, error } => format!("failed after {:?}: {}", elapsed, error),
+    };
+    html! {
+        <li>{format!("#{} {:?} ", event.id, event.kind)}<code>{event.statement.clone()}</code>{format!(" {}", phase)}</li>
+    }
+}
@@ -0,0 +1,69 @@
+use std::{error::Error, fmt::Display};
+
+use surrealdb::error::Api;
+
+use crate::TimedOut;
+
+/// Everything that can go wrong while talking to the database
+#[derive(Debug)]
+pub enum SurrealError {
+    /// The database or the connection reported an error
+    Database(surrealdb::Error),
+    /// The database did not respond in time
+    TimedOut(TimedOut),
+}
+
+pub type Result<T> = std::result::Result<T, SurrealError>;
+
+/// What kind of problem caused a `SurrealError`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The request could not be transported, e.g. the socket was closed or the client is not connected
+    Connection,
+    /// Reported by the database while executing the request or rejected by the client
+    Database,
+    /// The database did not respond in time
+    TimedOut,
+}
+
+impl SurrealError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            SurrealError::Database(surrealdb::Error::Api(
+                Api::Ws(_) | Api::Http(_) | Api::ConnectionUninitialised,
+            )) => ErrorKind::Connection,
+            SurrealError::Database(_) => ErrorKind::Database,
+            SurrealError::TimedOut(_) => ErrorKind::TimedOut,
+        }
+    }
+
+    /// Whether the database could not be reached at all
+    ///
+    /// A timeout does not count, the request may have reached the database and been applied
+    pub fn is_offline(&self) -> bool {
+        self.kind() == ErrorKind::Connection
+    }
+}
+
+impl Display for SurrealError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SurrealError::Database(error) => error.fmt(f),
+            SurrealError::TimedOut(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for SurrealError {}
+
+impl From<surrealdb::Error> for SurrealError {
+    fn from(value: surrealdb::Error) -> Self {
+        SurrealError::Database(value)
+    }
+}
+
+impl From<TimedOut> for SurrealError {
+    fn from(value: TimedOut) -> Self {
+        SurrealError::TimedOut(value)
+    }
+}
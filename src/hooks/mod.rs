@@ -1,21 +1,29 @@
 use lazy_static::lazy_static;
 use serde::{de::DeserializeOwned, ser::Serialize};
-use surrealdb::engine::remote::ws::{Client, Ws};
+use surrealdb::engine::any::Any as Client;
 use surrealdb::opt::auth::{Credentials, Signin};
 use surrealdb::Surreal;
 
-use yew::{hook,use_state, use_effect_with_deps};
+use yew::{hook, use_mut_ref, use_reducer, use_state, use_effect_with_deps};
 use yew::{use_callback, Callback};
 
+mod pending_request;
+mod use_auth;
 mod use_query_state;
 mod use_self_ref;
+mod use_sign_in;
 mod use_surreal;
 
+pub use pending_request::*;
+pub use use_auth::*;
 pub use use_query_state::*;
 pub use use_self_ref::*;
+pub use use_sign_in::*;
 pub use use_surreal::*;
 
+use crate::config::{ConnectionConfig, RetryPolicy};
 use crate::logging::handle_error;
+use crate::migrations::{MigrationState, Migrations};
 use crate::SurrealProps;
 use crate::props::id::HasID;
 
@@ -29,10 +37,21 @@ pub fn use_surreal_login<T>(
     login: impl Credentials<Signin, T> + 'static,
 ) -> SurrealToken
 where
-    surrealdb::method::Signin<'static, Client, T>:
-    std::future::IntoFuture<Output = surrealdb::Result<T>>,
+    T: DeserializeOwned + Send + Sync,
 {
-    use_surreal_login_with_client(&*STATIC_CLIENT, url, login)
+    use_surreal_login_with_config(url, login, ConnectionConfig::default())
+}
+
+#[hook]
+pub fn use_surreal_login_with_config<T>(
+    url: String,
+    login: impl Credentials<Signin, T> + 'static,
+    config: ConnectionConfig,
+) -> SurrealToken
+where
+    T: DeserializeOwned + Send + Sync,
+{
+    use_surreal_login_with_client(&*STATIC_CLIENT, url, login, config)
 }
 
 #[hook]
@@ -40,32 +59,150 @@ pub fn use_surreal_login_with_client<T>(
     client: &'static Surreal<Client>,
     url: String,
     login: impl Credentials<Signin, T> + 'static,
+    config: ConnectionConfig,
+) -> SurrealToken
+where
+    T: DeserializeOwned + Send + Sync,
+{
+    let token = use_surreal_token_with_client(client, config);
+    use_connect_and_sign_in(&token, url, login);
+    token
+}
+
+/// Like `use_surreal_login` but applies `migrations` after signing in, see `syewreal::migrations`
+///
+/// The `<SurrealContext/>` renders its children once every migration succeeded
+#[hook]
+pub fn use_surreal_login_with_migrations<T>(
+    url: String,
+    login: impl Credentials<Signin, T> + 'static,
+    migrations: Migrations,
 ) -> SurrealToken
 where
-    surrealdb::method::Signin<'static, Client, T>:
-    std::future::IntoFuture<Output = surrealdb::Result<T>>,
+    T: DeserializeOwned + Send + Sync,
 {
-    let ready = use_state(|| false);
+    let mut token = use_surreal_token();
+    token.migrations = Some(migrations);
+    use_connect_and_sign_in(&token, url, login);
+    token
+}
 
-    let ready_clone = ready.clone();
+/// Connect and sign in once the component was first rendered
+#[hook]
+fn use_connect_and_sign_in<T>(token: &SurrealToken, url: String, login: impl Credentials<Signin, T> + 'static)
+where
+    T: DeserializeOwned + Send + Sync,
+{
+    let token = token.clone();
     use_effect_with_deps(
         move |_| {
-            wasm_bindgen_futures::spawn_local(async move {
-                match client.connect::<Ws>(url).with_capacity(100000).await {
-                    surrealdb::Result::Ok(_) => match client.signin(login).await {
-                        surrealdb::Result::Ok(_) => {
-                            ready_clone.set(true);
-                        }
-                        Err(error) => handle_error(error),
-                    },
-                    Err(error) => handle_error(error),
+            yew::platform::spawn_local(async move {
+                if let Err(error) = token.connect_and_sign_in(url, login).await {
+                    handle_error(error);
                 }
             });
         },
         (),
     );
+}
 
-    SurrealToken { client, ready }
+/// Creates a `SurrealToken` backed by an embedded database that needs no server
+///
+/// `endpoint` is `mem://` (needs the `kv-mem` feature) or `indxdb://<name>` (needs the `kv-indxdb` feature)
+#[hook]
+pub fn use_surreal_embedded(endpoint: String, namespace: String, database: String) -> SurrealToken {
+    let token = use_surreal_token();
+
+    {
+        let token = token.clone();
+        use_effect_with_deps(
+            move |_| {
+                yew::platform::spawn_local(async move {
+                    if let Err(error) = token.connect_embedded(endpoint, namespace, database).await {
+                        handle_error(error);
+                    }
+                });
+            },
+            (),
+        );
+    }
+
+    token
+}
+
+/// Creates a `SurrealToken` that is not connected yet, use `SurrealToken::sign_in` to connect it
+#[hook]
+pub fn use_surreal_token() -> SurrealToken {
+    use_surreal_token_with_config(ConnectionConfig::default())
+}
+
+#[hook]
+pub fn use_surreal_token_with_config(config: ConnectionConfig) -> SurrealToken {
+    use_surreal_token_with_client(&*STATIC_CLIENT, config)
+}
+
+#[hook]
+pub fn use_surreal_token_with_client(client: &'static Surreal<Client>, config: ConnectionConfig) -> SurrealToken {
+    SurrealToken {
+        client,
+        ready: use_state(|| false),
+        revision: use_reducer(Revision::default),
+        auth: use_state(|| None),
+        config,
+        retry: RetryPolicy::default(),
+        offline: None,
+        #[cfg(feature = "testing")]
+        mock: None,
+        #[cfg(feature = "testing")]
+        recorder: None,
+        traffic: None,
+        metrics: None,
+        migrations: None,
+        migration_state: use_state(|| MigrationState::Idle),
+        caches: use_mut_ref(Vec::new),
+    }
+}
+
+/// Creates a `SurrealToken` for a client that is already connected and signed in
+///
+/// Meant for rendering on the server with the `ssr` feature where effects never run and the token can't connect itself.
+/// Nobody is signed in as far as the token knows, so `<RequireAuth/>` and `<RequirePermission/>` draw their fallback,
+/// use `use_surreal_connected_as` to render them for the user of the request
+#[hook]
+pub fn use_surreal_connected(client: &'static Surreal<Client>, config: ConnectionConfig) -> SurrealToken {
+    use_surreal_connected_with_auth(client, config, None)
+}
+
+/// Like `use_surreal_connected` for a client that is signed in as `auth`
+#[hook]
+pub fn use_surreal_connected_as(client: &'static Surreal<Client>, config: ConnectionConfig, auth: AuthInfo) -> SurrealToken {
+    use_surreal_connected_with_auth(client, config, Some(auth))
+}
+
+#[hook]
+fn use_surreal_connected_with_auth(
+    client: &'static Surreal<Client>,
+    config: ConnectionConfig,
+    auth: Option<AuthInfo>,
+) -> SurrealToken {
+    SurrealToken {
+        client,
+        ready: use_state(|| true),
+        revision: use_reducer(Revision::default),
+        auth: use_state(|| auth),
+        config,
+        retry: RetryPolicy::default(),
+        offline: None,
+        #[cfg(feature = "testing")]
+        mock: None,
+        #[cfg(feature = "testing")]
+        recorder: None,
+        traffic: None,
+        metrics: None,
+        migrations: None,
+        migration_state: use_state(|| MigrationState::Idle),
+        caches: use_mut_ref(Vec::new),
+    }
 }
 
 /// Updates the local and remote data of this component with the Properties returned by the closure
@@ -0,0 +1,51 @@
+use std::{future::Future, ops::Deref};
+
+use futures::future::{abortable, AbortHandle};
+use yew::suspense::Suspension;
+
+/// A request that is still running
+///
+/// Dereferences to the `Suspension` that resumes once the request finished or was aborted
+pub struct PendingRequest {
+    suspension: Suspension,
+    handle: AbortHandle,
+}
+
+impl PendingRequest {
+    pub(crate) fn new(future: impl Future<Output = ()> + 'static) -> Self {
+        let (future, handle) = abortable(future);
+        Self {
+            suspension: Suspension::from_future(async move {
+                let _ = future.await;
+            }),
+            handle,
+        }
+    }
+
+    /// Stop waiting for the request, the result will not be stored anywhere
+    pub fn abort(&self) {
+        self.handle.abort()
+    }
+
+    pub fn abort_handle(&self) -> AbortHandle {
+        self.handle.clone()
+    }
+
+    pub fn suspension(&self) -> Suspension {
+        self.suspension.clone()
+    }
+}
+
+impl Deref for PendingRequest {
+    type Target = Suspension;
+
+    fn deref(&self) -> &Self::Target {
+        &self.suspension
+    }
+}
+
+impl From<PendingRequest> for Suspension {
+    fn from(value: PendingRequest) -> Self {
+        value.suspension
+    }
+}
@@ -0,0 +1,68 @@
+use serde::Serialize;
+use serde_json::Value;
+use yew::hook;
+
+use super::use_surreal;
+
+/// The kind of user a `SurrealToken` is signed in as
+///
+/// Ordered by privilege, `Scope` is the least and `Root` the most privileged level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AuthLevel {
+    Scope,
+    Database,
+    Namespace,
+    Root,
+}
+
+/// Who a `SurrealToken` is signed in as
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthInfo {
+    /// The level of the credentials used to sign in, switching the database does not change it
+    pub level: AuthLevel,
+    /// The name of a root, namespace or database user
+    pub user: Option<String>,
+    pub namespace: Option<String>,
+    pub database: Option<String>,
+    pub scope: Option<String>,
+    /// The record of a scope user (`$auth`)
+    pub record: Option<Value>,
+}
+
+impl AuthInfo {
+    /// Read the namespace, database and scope from the credentials used to sign in
+    pub(crate) fn from_credentials(credentials: &impl Serialize) -> Self {
+        let credentials = serde_json::to_value(credentials).unwrap_or_default();
+        let field = |name: &str| credentials.get(name).and_then(Value::as_str).map(str::to_owned);
+        let (namespace, database, scope) = (field("NS"), field("DB"), field("SC"));
+        let level = match (&scope, &database, &namespace) {
+            (Some(_), _, _) => AuthLevel::Scope,
+            (None, Some(_), _) => AuthLevel::Database,
+            (None, None, Some(_)) => AuthLevel::Namespace,
+            (None, None, None) => AuthLevel::Root,
+        };
+        Self {
+            level,
+            user: field("user"),
+            namespace,
+            database,
+            scope,
+            record: None,
+        }
+    }
+
+    pub fn level(&self) -> AuthLevel {
+        self.level
+    }
+
+    /// Read a field of the `$auth` record
+    pub fn get(&self, field: &str) -> Option<&Value> {
+        self.record.as_ref().and_then(|record| record.get(field))
+    }
+}
+
+/// Returns who the current `SurrealToken` is signed in as or `None` if it is not signed in
+#[hook]
+pub fn use_auth() -> Option<AuthInfo> {
+    (*use_surreal().auth).clone()
+}
@@ -1,61 +1,56 @@
 use std::ops::Deref;
+use std::rc::Rc;
 
-use serde::de::DeserializeOwned;
-// use yew::hook;
+use serde::{de::DeserializeOwned, Serialize};
+use yew::{hook, use_effect_with_deps, use_mut_ref};
+#[cfg(feature = "devtools")]
+use yew::use_state;
+#[cfg(feature = "devtools")]
+use yew::Callback;
+use yew::functional::use_prepared_state;
 use yew::UseStateHandle;
 use yew::html::IntoPropValue;
-use yew::suspense::SuspensionResult;
+use yew::suspense::{Suspension, SuspensionResult};
 use yew::use_state_eq;
 
+use crate::cache::QueryCache;
+use crate::config::RetryPolicy;
 use crate::logging;
+use crate::metrics::Payload;
 use crate::props::selector::Selector;
 use crate::props::surreal_props::SurrealProps;
-use crate::hooks::use_surreal;
+use crate::hooks::{use_surreal, PendingRequest, SurrealToken};
+#[cfg(feature = "devtools")]
+use crate::devtools::{use_devtools_entry, QueryInfo, QueryStatus};
 
 // Recursive expansion of hook! macro
 // ===================================
 
 #[cfg(not(doctest))]
-#[doc = "\n# Note\n\nWhen used in function components and hooks, this hook is equivalent to:\n\n```\npub fn use_query_state<Props>(\n    selector: impl IntoPropValue<Selector>,\n) -> QueryState<Props::Remote>\nwhere\n    Props: SurrealProps,\n    Props::Remote: 'static + PartialEq + DeserializeOwned,\n{\n    /* implementation omitted */\n}\n\n```\n"]
+#[doc = "\n# Note\n\nWhen used in function components and hooks, this hook is equivalent to:\n\n```\npub fn use_query_state<Props>(\n    selector: impl IntoPropValue<Selector>,\n) -> QueryState<Props::Remote>\nwhere\n    Props: SurrealProps,\n    Props::Remote: 'static + PartialEq + DeserializeOwned + Serialize,\n{\n    /* implementation omitted */\n}\n\n```\n"]
 pub fn use_query_state<'hook, 'arg0, Props>(
     selector: impl 'arg0 + IntoPropValue<Selector>,
 ) -> impl 'hook + ::yew::functional::Hook<Output = QueryState<Props::Remote>>
 where
     Props: SurrealProps,
-    Props::Remote: 'static + PartialEq + DeserializeOwned,
+    Props::Remote: 'static + PartialEq + DeserializeOwned + Serialize,
     'arg0: 'hook,
     Props: 'hook,
 {
-    use yew::{suspense::Suspension, use_effect_with_deps};
-
     fn inner_fn<'hook, 'arg0, Props>(
         _ctx: &mut ::yew::functional::HookContext,
         selector: impl 'arg0 + IntoPropValue<Selector>,
     ) -> QueryState<Props::Remote>
     where
         Props: SurrealProps,
-        Props::Remote: 'static + PartialEq + DeserializeOwned,
+        Props::Remote: 'static + PartialEq + DeserializeOwned + Serialize,
         'arg0: 'hook,
         Props: 'hook,
     {
-        let sur = ::yew::functional::Hook::run(use_surreal(), _ctx);
-        let state: UseStateHandle<SuspensionResult<Vec<<Props as SurrealProps>::Remote>>> =
-            ::yew::functional::Hook::run(use_state_eq(|| Err(Suspension::new().0)), _ctx);
-        let selector = selector.into_prop_value();
-        let state_inner = state.clone();
-        {
-            let selector = selector.clone();
-            let state = state.clone();
-            ::yew::functional::Hook::run(use_effect_with_deps(move |_| {
-                state.set(Err(
-                    sur.query(selector.clone())
-                        .then(move |mut response| match response.take(0) {
-                            Ok(data) => state_inner.set(Ok(data)),
-                            Err(error) => logging::handle_error(error),
-                })));
-            }, ()), _ctx);
-        }
-        QueryState::<Props::Remote> { state: state, selector }
+        ::yew::functional::Hook::run(
+            use_query_state_with_options::<Props>(selector.into_prop_value(), QueryOptions::default()),
+            _ctx,
+        )
     }
     let boxed_inner = ::std::boxed::Box::new(
         move |_ctx: &mut ::yew::functional::HookContext| -> QueryState<Props::Remote> {
@@ -72,12 +67,12 @@ where
 pub fn use_query_state<Props>(selector: impl IntoPropValue<Selector>) -> QueryState<Props::Remote>
 where
     Props: SurrealProps,
-    Props::Remote: 'static + PartialEq + DeserializeOwned,
+    Props::Remote: 'static + PartialEq + DeserializeOwned + Serialize,
 {
     let sur = use_surreal();
     let state: UseStateHandle<Option<Vec<<Props as SurrealProps>::Remote>>> = use_state_eq(|| None);
     let selector = selector.into_prop_value();
-    sur.query(selector.clone())
+    selector.bind(sur.query(selector.clone()))
         .then(move |response| match response.take(0) {
             Ok(data) => state.set(Some(data)),
             Err(error) => logging::handle_error(error),
@@ -85,6 +80,225 @@ where
     QueryState::<Props::Remote> { state, selector }
 }
 
+/// Per query settings for `use_query_state_with_options`
+#[derive(Clone, PartialEq, Default)]
+pub struct QueryOptions {
+    /// Overrides the retry policy of the `<SurrealContext/>`
+    pub retry: Option<RetryPolicy>,
+    /// Persist the result, on the next mount the persisted data is shown immediately and refreshed in the background
+    pub cache: Option<QueryCache>,
+}
+
+/// Like `use_query_state` but with settings that only apply to this query
+#[hook]
+pub fn use_query_state_with_options<Props>(selector: Selector, options: QueryOptions) -> QueryState<Props::Remote>
+where
+    Props: SurrealProps,
+    Props::Remote: 'static + PartialEq + DeserializeOwned + Serialize,
+{
+    use_query_state_with_initial::<Props>(selector, options, None)
+}
+
+/// Like `use_query_state_with_options` but the query is already run while rendering on the server
+///
+/// The result is embedded into the rendered page and the client hydrates the `QueryState` from it instead of querying again.
+/// Needs the `ssr` feature on the server and the `hydration` feature on the client, without them this behaves like `use_query_state_with_options`
+#[hook]
+pub fn use_prepared_query_state<Props>(
+    selector: Selector,
+    options: QueryOptions,
+) -> SuspensionResult<QueryState<Props::Remote>>
+where
+    Props: SurrealProps,
+    Props::Remote: 'static + PartialEq + DeserializeOwned + Serialize,
+{
+    let sur = use_surreal();
+    let prepared = use_prepared_query::<Props::Remote>(&sur, &selector)?;
+    let initial = prepared.and_then(|data| Option::clone(&data));
+    Ok(use_query_state_with_initial::<Props>(selector, options, initial))
+}
+
+/// Runs the query on the server and passes the result on to the client, `None` if the query failed
+#[hook]
+fn use_prepared_query<Remote>(
+    token: &SurrealToken,
+    selector: &Selector,
+) -> SuspensionResult<Option<Rc<Option<Vec<Remote>>>>>
+where
+    Remote: 'static + DeserializeOwned + Serialize,
+{
+    let statement = selector
+        .base
+        .as_ref()
+        .map(|statement| statement.to_string())
+        .unwrap_or_default();
+    // the statement and its bindings identify the prepared result
+    let key = (statement, selector.bindings.json().to_string());
+    // the closure is stripped from builds without the `ssr` feature
+    #[cfg(not(feature = "ssr"))]
+    let _ = (token, selector);
+    #[cfg(feature = "ssr")]
+    let (token, selector) = (token.clone(), selector.clone());
+    use_prepared_state!(
+        async move |_| -> Option<Vec<Remote>> {
+            let mut response = selector.bind(token.query(selector.clone())).send().await.ok()?;
+            response.take(0).ok()
+        },
+        key
+    )
+}
+
+#[hook]
+fn use_query_state_with_initial<Props>(
+    selector: Selector,
+    options: QueryOptions,
+    initial: Option<Vec<Props::Remote>>,
+) -> QueryState<Props::Remote>
+where
+    Props: SurrealProps,
+    Props::Remote: 'static + PartialEq + DeserializeOwned + Serialize,
+{
+    let sur = use_surreal();
+    if let Some(cache) = &options.cache {
+        let mut caches = sur.caches.borrow_mut();
+        if !caches.contains(cache) {
+            caches.push(cache.clone());
+        }
+    }
+    let cache = options
+        .cache
+        .clone()
+        .and_then(|cache| Some((QueryCache::key(&sur, &selector)?, cache)));
+    // the hydrated data is as fresh as it gets, only query again once the token changes
+    let hydrated = use_mut_ref(|| initial.is_some());
+    let state: UseStateHandle<SuspensionResult<Vec<<Props as SurrealProps>::Remote>>> =
+        use_state_eq(|| {
+            match initial.or_else(|| cache.as_ref().and_then(|(key, cache)| cache.load(key))) {
+                Some(data) => Ok(data),
+                None => Err(Suspension::new().0),
+            }
+        });
+    // why the last request failed (`None` once it succeeded) and a counter to query again, only the devtools use them
+    #[cfg(feature = "devtools")]
+    let error = use_state_eq(|| None::<String>);
+    #[cfg(feature = "devtools")]
+    let refetch = use_state(|| 0usize);
+    #[cfg(feature = "devtools")]
+    let refetched = *refetch;
+    #[cfg(not(feature = "devtools"))]
+    let refetched = 0usize;
+    let state_inner = state.clone();
+    {
+        let selector = selector.clone();
+        let state = state.clone();
+        let report = {
+            #[cfg(feature = "devtools")]
+            let error = error.clone();
+            move |reason: Option<String>| {
+                #[cfg(feature = "devtools")]
+                error.set(reason);
+                #[cfg(not(feature = "devtools"))]
+                let _ = reason;
+            }
+        };
+        let cache = cache.clone();
+        // refetch whenever the token switches to different data
+        let deps = (*sur.revision, *sur.ready, refetched);
+        use_effect_with_deps(move |(_, ready, _)| {
+            let request = (*ready && !hydrated.replace(false)).then(|| {
+                let mut query = selector.bind(sur.query(selector.clone()));
+                if let Some(retry) = options.retry {
+                    query = query.retry(retry);
+                }
+                let request = PendingRequest::new(async move {
+                    let (response, measure) = query.send_measured().await;
+                    let data = match response {
+                        Ok(mut response) => response.take::<Vec<Props::Remote>>(0),
+                        Err(reason) => {
+                            report(Some(reason.to_string()));
+                            return logging::handle_error(reason);
+                        }
+                    };
+                    measure.finish(&data, |data| Payload::of(data));
+                    match data {
+                        Ok(data) => {
+                            if let Some((key, cache)) = cache {
+                                cache.store::<Props::Remote>(&key, &data);
+                            }
+                            report(None);
+                            state_inner.set(Ok(data))
+                        }
+                        Err(reason) => {
+                            report(Some(reason.to_string()));
+                            logging::handle_error(reason)
+                        }
+                    }
+                });
+                // keep showing the persisted data while it is refreshed
+                if state.is_err() {
+                    state.set(Err(request.suspension()));
+                }
+                request
+            });
+            // don't touch the state once the component is gone or the query was restarted
+            move || {
+                if let Some(request) = request {
+                    request.abort();
+                }
+            }
+        }, deps);
+    }
+
+    #[cfg(feature = "devtools")]
+    {
+        let refetch = {
+            let refetch = refetch.clone();
+            Callback::from(move |_| refetch.set(*refetch + 1))
+        };
+        // drop the local and the persisted data and show the fallback until the query returned
+        let invalidate = {
+            let state = state.clone();
+            let refetch = refetch.clone();
+            Callback::from(move |_| {
+                if let Some((key, cache)) = &cache {
+                    cache.remove(key);
+                }
+                state.set(Err(Suspension::new().0));
+                refetch.emit(());
+            })
+        };
+        let status = match (&*state, &*error) {
+            (Ok(_), _) => QueryStatus::Ok,
+            (Err(_), Some(error)) => QueryStatus::Error(error.clone()),
+            (Err(_), None) => QueryStatus::Loading,
+        };
+        let count = match &*state {
+            Ok(data) => data.len(),
+            Err(_) => 0,
+        };
+        // the records are only serialized while the panel shows them
+        let records = {
+            let state = state.clone();
+            Rc::new(move || match &*state {
+                Ok(data) => data.iter().filter_map(|record| serde_json::to_value(record).ok()).collect::<Vec<_>>(),
+                Err(_) => vec![],
+            })
+        };
+        use_devtools_entry(QueryInfo {
+            selector: selector.base.as_ref().map(ToString::to_string).unwrap_or_default(),
+            parameters: selector.bindings.json().clone(),
+            status,
+            count,
+            records,
+            data: &*state as *const _ as *const (),
+            refetch,
+            invalidate,
+        });
+    }
+
+    QueryState::<Props::Remote> { state, selector }
+}
+
 #[derive(Clone, PartialEq)]
 pub struct QueryState<Remote> {
     state: UseStateHandle<SuspensionResult<Vec<Remote>>>,
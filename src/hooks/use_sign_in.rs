@@ -0,0 +1,66 @@
+use serde::de::DeserializeOwned;
+use surrealdb::opt::auth::{Credentials, Signin};
+use yew::{hook, use_state_eq, suspense::Suspension, Callback, UseStateHandle};
+
+use super::{SignInError, SurrealToken};
+
+/// The progress of the last sign in started through a `UseSignInHandle`
+#[derive(Debug, Clone, PartialEq)]
+pub enum SignInState {
+    Idle,
+    Loading,
+    Success,
+    Failed(SignInError),
+}
+
+/// Signs a `SurrealToken` in and keeps track of the outcome
+#[hook]
+pub fn use_sign_in(token: SurrealToken) -> UseSignInHandle {
+    let state = use_state_eq(|| SignInState::Idle);
+    UseSignInHandle { token, state }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct UseSignInHandle {
+    token: SurrealToken,
+    state: UseStateHandle<SignInState>,
+}
+
+impl UseSignInHandle {
+    pub fn state(&self) -> &SignInState {
+        &self.state
+    }
+
+    pub fn is_loading(&self) -> bool {
+        *self.state == SignInState::Loading
+    }
+
+    pub fn error(&self) -> Option<&SignInError> {
+        match &*self.state {
+            SignInState::Failed(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    pub fn token(&self) -> &SurrealToken {
+        &self.token
+    }
+
+    pub fn sign_in<R>(&self, url: String, credentials: impl 'static + Credentials<Signin, R>) -> Suspension
+    where
+        R: DeserializeOwned + Send + Sync,
+    {
+        let state = self.state.clone();
+        self.state.set(SignInState::Loading);
+        self.token.sign_in(
+            url,
+            credentials,
+            Callback::from(move |result| {
+                state.set(match result {
+                    Ok(()) => SignInState::Success,
+                    Err(error) => SignInState::Failed(error),
+                })
+            }),
+        )
+    }
+}
@@ -1,22 +1,42 @@
-use std::{fmt::Display, error::Error};
+use std::{cell::RefCell, fmt::Display, error::Error, rc::Rc, time::Duration};
 
 use serde::{de::DeserializeOwned, Serialize};
 use surrealdb::{
-    engine::remote::ws::{Client, Ws},
+    engine::any::Any as Client,
     method::{Content, Query, Select},
     opt::{
         auth::{Credentials, Signin},
-        IntoQuery, IntoResource,
+        IntoQuery, IntoResource, Resource, Strict,
     },
     sql::{statements::SelectStatement, Value, Values},
-    Connection, Response, Result, Surreal,
+    Connection, Response, Surreal,
 };
-use yew::{hook, use_context, UseStateHandle, suspense::Suspension};
+use yew::{hook, use_context, Callback, Reducible, UseReducerHandle, UseStateHandle, suspense::Suspension};
 use async_trait::async_trait;
 
-use crate::{props::id::HasID, props::surreal_props::SurrealProps, logging::{handle_error, self}};
+use crate::{
+    cache::QueryCache,
+    config::{ConnectionConfig, RetryPolicy},
+    error::{Result, SurrealError},
+    props::id::HasID,
+    props::surreal_props::SurrealProps,
+    query::BoundQuery,
+    DELETE, UPDATE,
+    schema::SurrealSchema,
+    logging::{handle_error, self},
+    offline::{send_or_queue, send_or_queue_with, Mutation, OfflineQueue, Queued},
+    retry::send,
+    intercept::{Intercept, Measure},
+    metrics::{Payload, QueryMetrics},
+    migrations::{MigrationState, Migrations},
+    traffic::{RequestKind, TrafficLog},
+    timeout::{timeout, TimedOut},
+};
+
+#[cfg(feature = "testing")]
+use crate::testing::{MockDatabase, TrafficRecorder};
 
-use super::{QueryState, SurrealSelfRef};
+use super::{AuthInfo, AuthLevel, PendingRequest, QueryState, SurrealSelfRef};
 
 #[derive(Debug)]
 struct NoSurrealContext;
@@ -29,6 +49,33 @@ impl Display for NoSurrealContext {
 
 impl Error for NoSurrealContext {}
 
+/// The reason a sign in attempt failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum SignInError {
+    /// The connection to the database could not be established
+    Connection(String),
+    /// The connection was established but the database rejected the credentials
+    Authentication(String),
+    /// The database did not respond within the timeout of the `ConnectionConfig`
+    Timeout(Duration),
+    /// A migration of the token failed, `None` if the applied versions could not be read
+    Migration(Option<u64>, String),
+}
+
+impl Display for SignInError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignInError::Connection(reason) => write!(f, "Could not connect to the database: {}", reason),
+            SignInError::Authentication(reason) => write!(f, "Could not sign in: {}", reason),
+            SignInError::Timeout(duration) => write!(f, "The database did not respond within {:?}", duration),
+            SignInError::Migration(Some(version), reason) => write!(f, "Migration {} failed: {}", version, reason),
+            SignInError::Migration(None, reason) => write!(f, "Could not read the applied migrations: {}", reason),
+        }
+    }
+}
+
+impl Error for SignInError {}
+
 
 #[hook]
 pub fn use_surreal() -> SurrealToken {
@@ -38,46 +85,248 @@ pub fn use_surreal() -> SurrealToken {
     }
 }
 
+/// Counts the changes of the data behind a connection, see `SurrealToken::refetch`
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Revision(pub usize);
+
+impl Reducible for Revision {
+    type Action = ();
+
+    fn reduce(self: std::rc::Rc<Self>, _: ()) -> std::rc::Rc<Self> {
+        Revision(self.0 + 1).into()
+    }
+}
+
 #[derive(Clone)]
 pub struct SurrealToken {
     pub client: &'static Surreal<Client>,
     pub ready: UseStateHandle<bool>,
+    /// incremented whenever the data behind the connection changes, e.g. after switching the database
+    pub revision: UseReducerHandle<Revision>,
+    /// who the token is signed in as, `None` until the sign in succeeded
+    pub auth: UseStateHandle<Option<AuthInfo>>,
+    pub config: ConnectionConfig,
+    /// used for every request unless overridden
+    pub retry: RetryPolicy,
+    /// collects mutations while the database can't be reached
+    pub offline: Option<OfflineQueue>,
+    /// answers and records requests in tests, see `<MockSurrealContext/>`
+    #[cfg(feature = "testing")]
+    pub mock: Option<MockDatabase>,
+    /// records queries and their responses so they can be replayed later
+    #[cfg(feature = "testing")]
+    pub recorder: Option<TrafficRecorder>,
+    /// logs every request
+    pub traffic: Option<TrafficLog>,
+    /// measures every request
+    pub metrics: Option<QueryMetrics>,
+    /// applied after connecting, the token only becomes ready once all of them succeeded
+    pub migrations: Option<Migrations>,
+    pub migration_state: UseStateHandle<MigrationState>,
+    /// the caches used by the `QueryState`s of this token, cleared on `sign_out`
+    pub(crate) caches: Rc<RefCell<Vec<QueryCache>>>,
 }
 
 impl PartialEq for SurrealToken {
     fn eq(&self, other: &Self) -> bool {
+        #[cfg(feature = "testing")]
+        if self.mock != other.mock || self.recorder != other.recorder {
+            return false;
+        }
         self.ready == other.ready
+            && self.revision == other.revision
+            && self.auth == other.auth
+            && self.config == other.config
+            && self.retry == other.retry
+            && self.offline == other.offline
+            && self.traffic == other.traffic
+            && self.metrics == other.metrics
+            && self.migrations == other.migrations
+            && self.migration_state == other.migration_state
     }
 }
 
 impl SurrealToken {
-    pub fn sign_in<R>(&self, url: String, credentials: impl 'static + Credentials<Signin, R>)
+    /// Record every query of this token and its response, pass the token to `<SurrealContext/>` afterwards
+    #[cfg(feature = "testing")]
+    pub fn with_recorder(mut self, recorder: TrafficRecorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Connect to `url` and sign in, dependents are suspended until the sign in succeeded
+    ///
+    /// The outcome is reported to `callback`
+    pub fn sign_in<R>(
+        &self,
+        url: String,
+        credentials: impl 'static + Credentials<Signin, R>,
+        callback: Callback<std::result::Result<(), SignInError>>,
+    ) -> Suspension
     where
         R: DeserializeOwned + Send + Sync,
     {
-        let client = self.client;
-        let ready = self.ready.clone();
+        let token = self.clone();
 
         self.ready.set(false);
 
         Suspension::from_future(async move {
-            match client.connect::<Ws>(url).with_capacity(100000).await {
-                surrealdb::Result::Ok(()) => match client.signin(credentials).await {
-                    surrealdb::Result::Ok(_) => {
-                        ready.set(true);
-                    }
-                    Err(error) => handle_error(error),
-                },
-                Err(error) => handle_error(error),
+            callback.emit(token.connect_and_sign_in(url, credentials).await);
+        })
+    }
+
+    /// Connect to `url` and sign in, marks the token as ready on success
+    pub async fn connect_and_sign_in<R>(
+        &self,
+        url: String,
+        credentials: impl Credentials<Signin, R>,
+    ) -> std::result::Result<(), SignInError>
+    where
+        R: DeserializeOwned + Send + Sync,
+    {
+        self.connect(url).await?;
+        let mut auth = AuthInfo::from_credentials(&credentials);
+        match timeout(self.config.timeout, self.client.signin(credentials)).await {
+            Ok(Ok(_)) => (),
+            Ok(Err(error)) => return Err(SignInError::Authentication(error.to_string())),
+            Err(TimedOut(duration)) => return Err(SignInError::Timeout(duration)),
+        }
+        // only scope users have an $auth record, other users simply get none
+        auth.record = match self.client.query("SELECT * FROM $auth").await {
+            Ok(mut response) => response.take::<Option<serde_json::Value>>(0).unwrap_or_default(),
+            Err(_) => None,
+        };
+        self.auth.set(Some(auth));
+        self.migrate().await?;
+        self.ready.set(true);
+        Ok(())
+    }
+
+    /// Open an embedded database (`mem://` or `indxdb://<name>`) and select `ns` and `db`, no sign in is needed
+    pub async fn connect_embedded(
+        &self,
+        endpoint: String,
+        ns: String,
+        db: String,
+    ) -> std::result::Result<(), SignInError> {
+        self.connect(endpoint).await?;
+        self.use_embedded(ns, db).await
+    }
+
+    /// Select `ns` and `db` of an embedded database the client is already connected to
+    pub(crate) async fn use_embedded(&self, ns: String, db: String) -> std::result::Result<(), SignInError> {
+        match timeout(self.config.timeout, self.client.use_ns(ns.clone()).use_db(db.clone())).await {
+            Ok(Ok(())) => (),
+            Ok(Err(error)) => return Err(SignInError::Connection(error.to_string())),
+            Err(TimedOut(duration)) => return Err(SignInError::Timeout(duration)),
+        }
+        // embedded databases have no users, the token has full access
+        self.auth.set(Some(AuthInfo {
+            level: AuthLevel::Root,
+            user: None,
+            namespace: Some(ns),
+            database: Some(db),
+            scope: None,
+            record: None,
+        }));
+        self.migrate().await?;
+        self.ready.set(true);
+        Ok(())
+    }
+
+    /// Sign out and clear every `QueryCache` used with this token so the next user does not see the data of this one
+    ///
+    /// Dependents are suspended until the token signs in again
+    pub async fn sign_out(&self) -> Result<()> {
+        for cache in self.caches.borrow().iter() {
+            cache.clear();
+        }
+        self.auth.set(None);
+        self.ready.set(false);
+        self.client.invalidate().await?;
+        Ok(())
+    }
+
+    async fn migrate(&self) -> std::result::Result<(), SignInError> {
+        match &self.migrations {
+            Some(migrations) => migrations.run(self).await,
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) async fn connect(&self, url: String) -> std::result::Result<(), SignInError> {
+        let config = &self.config;
+        let endpoint = config.endpoint(url);
+        let connected = match config.strict {
+            true => {
+                let connect = self.client.connect((endpoint, Strict)).with_capacity(config.capacity);
+                timeout(config.timeout, connect).await
             }
-        });
+            false => {
+                let connect = self.client.connect(endpoint).with_capacity(config.capacity);
+                timeout(config.timeout, connect).await
+            }
+        };
+        match connected {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(error)) => Err(SignInError::Connection(error.to_string())),
+            Err(TimedOut(duration)) => Err(SignInError::Timeout(duration)),
+        }
+    }
+
+    /// Switch the namespace and database of the existing connection without signing in again
+    ///
+    /// Dependents are suspended until the switch is done, afterwards all active `QueryState`s are refetched.
+    /// The outcome is reported to `callback`, if the switch failed the token keeps its previous state
+    pub fn use_ns_db(
+        &self,
+        ns: impl Into<String>,
+        db: impl Into<String>,
+        callback: Callback<Result<()>>,
+    ) -> Suspension {
+        let token = self.clone();
+        let was_ready = *self.ready;
+        let (ns, db) = (ns.into(), db.into());
+
+        self.ready.set(false);
+
+        Suspension::from_future(async move {
+            match token.client.use_ns(ns.clone()).use_db(db.clone()).await {
+                Ok(()) => {
+                    token.auth.set((*token.auth).clone().map(|auth| AuthInfo {
+                        namespace: Some(ns),
+                        database: Some(db),
+                        ..auth
+                    }));
+                    token.refetch();
+                    token.ready.set(true);
+                    callback.emit(Ok(()));
+                }
+                Err(error) => {
+                    token.ready.set(was_ready);
+                    callback.emit(Err(error.into()));
+                }
+            }
+        })
+    }
+
+    /// Mark the data behind the connection as changed, every active `QueryState` is refetched
+    pub fn refetch(&self) {
+        self.revision.dispatch(());
     }
 
     pub fn select<R: DeserializeOwned>(
         &self,
-        resource: impl IntoResource<R>,
+        resource: impl IntoResource<R> + Clone + 'static,
     ) -> SurrealSelect<Client, R> {
-        SurrealSelect(self.client.select(resource))
+        let intercept = Intercept::describe(self, RequestKind::Select, "SELECT * FROM", resource.clone().into_db_resource());
+        let client = self.client;
+        SurrealSelect {
+            make: Box::new(move || client.select(resource.clone())),
+            timeout: self.config.timeout,
+            retry: self.retry.clone(),
+            intercept,
+        }
     }
 
     pub fn update<R>(&self, what: &SurrealSelfRef<R>) -> SurrealUpdate<R>
@@ -85,19 +334,96 @@ impl SurrealToken {
         R: SurrealProps + Clone,
         R::Remote: Clone,
     {
-        SurrealUpdate(self.clone(), (*what).clone())
+        let mut token = self.clone();
+        token.retry = token.retry.for_mutation();
+        SurrealUpdate(token, (*what).clone())
+    }
+
+    pub fn query(&self, query: impl IntoQuery + Clone + 'static) -> SurrealQuery<Client> {
+        let intercept = Intercept::new(self, RequestKind::Query, query.clone());
+        let client = self.client;
+        #[cfg(feature = "testing")]
+        let fixture = self.mock.as_ref().and_then(|mock| mock.rows(query.clone()));
+        #[cfg(not(feature = "testing"))]
+        let fixture: Option<Vec<serde_json::Value>> = None;
+        let make: Box<dyn Fn() -> Query<'static, Client>> =
+            match fixture {
+                // the in-memory database hands the fixture back as the result of the query
+                Some(rows) => Box::new(move || {
+                    client
+                        .query("SELECT * FROM $fixture")
+                        .bind(("fixture", rows.clone()))
+                }),
+                None => Box::new(move || client.query(query.clone())),
+            };
+        SurrealQuery {
+            make,
+            timeout: self.config.timeout,
+            retry: self.retry.clone(),
+            intercept,
+        }
+    }
+
+    /// Send a query built by `bound_sqlx!` together with its bindings
+    pub fn bound_query<P>(&self, query: BoundQuery<P>) -> SurrealQuery<Client>
+    where
+        P: Serialize + Clone + 'static,
+    {
+        self.query(query.query).bind(query.bindings)
     }
 
-    pub fn query(&self, query: impl IntoQuery) -> SurrealQuery<Client> {
-        SurrealQuery(self.client.query(query))
+    /// Define the table and the fields of `S`, e.g. `token.define_schema::<ToDoItemProps>().execute()`
+    pub fn define_schema<S: SurrealSchema>(&self) -> SurrealQuery<Client> {
+        self.query(S::schema())
     }
 
-    pub fn create<R: Serialize + DeserializeOwned + Send + Sync, D: Serialize + Send + Sync>(
+    pub fn create<R, D>(
         &self,
-        id: impl IntoResource<Vec<R>>,
+        id: impl IntoResource<Vec<R>> + Clone + 'static,
         data: D,
-    ) -> SurrealCreate<Client, D, R> {
-        SurrealCreate(self.client.create(id).content(data))
+    ) -> SurrealCreate<Client, D, R>
+    where
+        R: Serialize + DeserializeOwned + Send + Sync,
+        D: Serialize + Send + Sync + Clone + 'static,
+    {
+        let queued = self.offline.clone().and_then(|queue| {
+            let what = match id.clone().into_db_resource() {
+                Ok(Resource::Table(table)) => table.0,
+                Ok(Resource::RecordId(thing)) => thing.to_string(),
+                _ => return None,
+            };
+            let data = serde_json::to_value(&data).ok()?;
+            Some(Queued {
+                queue,
+                token: self.clone(),
+                optimistic: serde_json::from_value(data.clone()).ok(),
+                mutation: Mutation::Create { what, data },
+            })
+        });
+        let intercept = Intercept::describe(self, RequestKind::Create, "CREATE", id.clone().into_db_resource())
+            .map(|mut intercept| {
+                intercept.bind(&data);
+                intercept
+            });
+        let client = self.client;
+        SurrealCreate {
+            make: Box::new(move || client.create(id.clone()).content(data.clone())),
+            timeout: self.config.timeout,
+            retry: self.retry.for_mutation(),
+            queued,
+            intercept,
+        }
+    }
+
+    /// Delete the record of a component and drop it from the local data
+    pub fn delete<R>(&self, what: &SurrealSelfRef<R>) -> SurrealDelete<R>
+    where
+        R: SurrealProps + Clone,
+        R::Remote: Clone,
+    {
+        let mut token = self.clone();
+        token.retry = token.retry.for_mutation();
+        SurrealDelete(token, (*what).clone())
     }
 }
 
@@ -107,45 +433,72 @@ impl<R: 'static + SurrealProps + HasID> SurrealUpdate<R>
 where
     <R as SurrealProps>::Remote: Clone + DeserializeOwned + Serialize + Send + Sync,
 {
+    /// Give up if the database does not respond within `duration`
+    pub fn timeout(mut self, duration: Duration) -> Self {
+        self.0.config.timeout = Some(duration);
+        self
+    }
+
+    /// Resend the update according to `policy` if it fails
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.0.retry = policy;
+        self
+    }
+
     /// Send the given data to the DB and update the local data if the new data still matches the original query
     ///
     /// Always uses MERGE because R may not include all fields of the underlying data
-    pub fn with(self, data: R) -> Suspension {
-        if let Ok(mut query) = TryInto::<SelectStatement>::try_into(self.1.state.get_selector()) {
+    pub fn with(self, data: R) -> PendingRequest {
+        let selector = self.1.state.get_selector();
+        if let Ok(mut query) = TryInto::<SelectStatement>::try_into(selector.clone()) {
             let id = (*data.id()).clone();
 
             query.what = Values(vec![Value::Thing(id.clone())]);
 
             logging::print_traffic(logging::Operation::Update, &data.get_remote());
 
-            Suspension::from_future(async move {
-                match self
-                    .0
-                    .client
-                    .query("UPDATE $thing MERGE $data RETURN NONE")
-                    .bind(("thing", id))
-                    .bind(("data", data.get_remote()))
-                    .query(query)
-                    .await
-                {
-                    Ok(mut data) => match data.take::<Option<R::Remote>>(1) {
-                        Ok(data) => {
-                            logging::print_traffic(logging::Operation::Receive, &data);
-                            self.1.set(data)
-                        },
-                        Err(error) => handle_error(error),
+            let queued = self.0.offline.clone().and_then(|queue| {
+                Some(Queued {
+                    queue,
+                    token: self.0.clone(),
+                    mutation: Mutation::Update {
+                        thing: id.to_string(),
+                        data: serde_json::to_value(data.get_remote()).ok()?,
                     },
-                    Err(error) => handle_error(error),
+                    optimistic: Some(Some(data.get_remote())),
+                })
+            });
+
+            // the selector reads the record back, it may need its own params
+            let request = self
+                .0
+                .query(UPDATE!($thing MERGE $data RETURN NONE))
+                .bind(("thing", id))
+                .bind(("data", data.get_remote()))
+                .query(query);
+            let request = selector.bind(request).kind(RequestKind::Update);
+
+            PendingRequest::new(async move {
+                let request = async move {
+                    let (response, measure) = request.send_measured().await;
+                    let data = response?.take::<Option<R::Remote>>(1);
+                    measure.finish(&data, |data| Payload::of(data));
+                    Ok::<_, SurrealError>(data?)
                 };
+                send_or_queue(request, queued, |data| {
+                    logging::print_traffic(logging::Operation::Receive, &data);
+                    self.1.set(data)
+                })
+                .await
             })
         } else {
-            Suspension::new().0
+            PendingRequest::new(async {})
         }
     }
 
     /// Retrieve the current data from the DB and update this component if needed
-    pub fn refresh(self) -> Suspension {
-        Suspension::from_future(async move {
+    pub fn refresh(self) -> PendingRequest {
+        PendingRequest::new(async move {
             match self.0.select((*self.1.id).clone()).resolve().await {
                 Ok(Some(data)) => self.1.set(Some(data)),
                 Ok(_) => (),
@@ -155,8 +508,8 @@ where
     }
 
     /// Retrieve the current data from the DB and update this component or drop it if the record no longer exists
-    pub fn refresh_or_drop(self) -> Suspension {
-        Suspension::from_future(async move {
+    pub fn refresh_or_drop(self) -> PendingRequest {
+        PendingRequest::new(async move {
             match self.0.select((*self.1.id).clone()).resolve().await {
                 Ok(data) => self.1.set(data),
                 Err(error) => handle_error(error),
@@ -165,17 +518,90 @@ where
     }
 }
 
-pub struct SurrealSelect<C: Connection, R: DeserializeOwned>(Select<'static, C, R>);
+pub struct SurrealDelete<R: SurrealProps>(SurrealToken, SurrealSelfRef<R>);
+
+impl<R: 'static + SurrealProps> SurrealDelete<R>
+where
+    <R as SurrealProps>::Remote: Clone,
+{
+    /// Give up if the database does not respond within `duration`
+    pub fn timeout(mut self, duration: Duration) -> Self {
+        self.0.config.timeout = Some(duration);
+        self
+    }
 
-#[async_trait]
+    /// Resend the delete according to `policy` if it fails
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.0.retry = policy;
+        self
+    }
+
+    pub fn execute(self) -> PendingRequest {
+        let thing = (*self.1.id).clone();
+        let queued = self.0.offline.clone().map(|queue| Queued {
+            queue,
+            token: self.0.clone(),
+            mutation: Mutation::Delete {
+                thing: thing.to_string(),
+            },
+            optimistic: Some(()),
+        });
+        let request = self
+            .0
+            .query(DELETE!($thing))
+            .bind(("thing", thing))
+            .kind(RequestKind::Delete);
+
+        PendingRequest::new(async move {
+            let request = async move {
+                let mut response = request.send().await?;
+                response.take::<Vec<serde_json::Value>>(0)?;
+                Ok::<_, SurrealError>(())
+            };
+            send_or_queue(request, queued, |()| self.1.set(None)).await
+        })
+    }
+}
+
+pub struct SurrealSelect<C: Connection, R: DeserializeOwned> {
+    make: Box<dyn Fn() -> Select<'static, C, R>>,
+    timeout: Option<Duration>,
+    retry: RetryPolicy,
+    intercept: Option<Intercept>,
+}
+
+#[async_trait(?Send)]
 impl<Client, D> Fetch for SurrealSelect<Client, Option<D>>
 where
     Client: Connection,
-    D: Clone + DeserializeOwned + Send + Sync + 'static,
+    D: Clone + DeserializeOwned + Serialize + Send + Sync + 'static,
 {
     type Target = D;
     async fn resolve(self) -> Result<Self::Target> {
-        self.0.await
+        let intercept = match self.intercept {
+            Some(intercept) => intercept,
+            None => return send(self.make, self.timeout, &self.retry).await,
+        };
+        let result = match intercept.respond_value().await? {
+            Some(data) => Ok(data),
+            None => {
+                let result = send(self.make, self.timeout, &self.retry).await;
+                intercept.record_value(&result);
+                result
+            }
+        };
+        intercept.measure(&result, |_| Payload::unknown());
+        result
+    }
+
+    fn timeout(mut self, duration: Duration) -> Self {
+        self.timeout = Some(duration);
+        self
+    }
+
+    fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
     }
 }
 
@@ -183,9 +609,28 @@ pub struct SurrealCreate<
     C: Connection,
     D: Serialize + Send + Sync,
     R: DeserializeOwned + Serialize + Send + Sync,
->(Content<'static, C, D, R>);
+> {
+    make: Box<dyn Fn() -> Content<'static, C, D, R>>,
+    timeout: Option<Duration>,
+    retry: RetryPolicy,
+    queued: Option<Queued<R>>,
+    intercept: Option<Intercept>,
+}
+
+impl<C, D, R> SurrealCreate<C, D, R>
+where
+    C: Connection,
+    D: 'static + Serialize + Send + Sync,
+    R: 'static + Clone + DeserializeOwned + Serialize + Send + Sync,
+{
+    /// Like `handle` but queues the create if the database can't be reached, `f` receives the local data in that case
+    fn send_or_queue(mut self, f: impl 'static + FnOnce(Result<R>)) -> PendingRequest {
+        let queued = self.queued.take();
+        PendingRequest::new(async move { send_or_queue_with(self.resolve(), queued, f).await })
+    }
+}
 
-#[async_trait]
+#[async_trait(?Send)]
 impl<C, D, R> Fetch for SurrealCreate<C, D, R>
 where
     C: Connection,
@@ -194,30 +639,144 @@ where
 {
     type Target = R;
     async fn resolve(self) -> Result<Self::Target> {
-        self.0.await
+        let intercept = match self.intercept {
+            Some(intercept) => intercept,
+            None => return send(self.make, self.timeout, &self.retry).await,
+        };
+        let result = match intercept.respond_value().await? {
+            Some(data) => Ok(data),
+            None => {
+                let result = send(self.make, self.timeout, &self.retry).await;
+                intercept.record_value(&result);
+                result
+            }
+        };
+        intercept.measure(&result, |data| Payload::of(std::iter::once(data)));
+        result
+    }
+
+    fn timeout(mut self, duration: Duration) -> Self {
+        self.timeout = Some(duration);
+        self
+    }
+
+    fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    fn handle<F: 'static + FnOnce(Result<Self::Target>) -> ()>(self, f: F) -> PendingRequest {
+        self.send_or_queue(f)
+    }
+
+    fn then<F: 'static + FnOnce(Self::Target) -> ()>(self, f: F) -> PendingRequest {
+        self.send_or_queue(|result| {
+            result.ok().map(f);
+        })
+    }
+
+    fn store_to(self, state: &UseStateHandle<Option<Self::Target>>) -> PendingRequest {
+        let state = state.clone();
+        self.send_or_queue(move |result| match result {
+            Ok(data) => state.set(Some(data)),
+            Err(error) => handle_error(error),
+        })
+    }
+
+    fn store_or_drop(self, state: &UseStateHandle<Option<Self::Target>>) -> PendingRequest {
+        self.store_to(state)
+    }
+
+    fn append_to(self, result_list: QueryState<Self::Target>) -> PendingRequest {
+        self.send_or_queue(move |result| match result {
+            Ok(data) => result_list.append(data),
+            Err(error) => handle_error(error),
+        })
+    }
+
+    fn execute(self) -> PendingRequest {
+        self.send_or_queue(|result| {
+            if let Err(error) = result {
+                handle_error(error)
+            }
+        })
     }
 }
 
-pub struct SurrealQuery<C: Connection>(Query<'static, C>);
+pub struct SurrealQuery<C: Connection> {
+    make: Box<dyn Fn() -> Query<'static, C>>,
+    timeout: Option<Duration>,
+    retry: RetryPolicy,
+    intercept: Option<Intercept>,
+}
 
 impl<C: Connection> SurrealQuery<C> {
-    pub fn execute(self) -> Suspension {
-        Suspension::from_future(async move {
-            let _ = self.0.await;
+    pub(crate) async fn send(self) -> Result<Response> {
+        let (response, measure) = self.send_measured().await;
+        measure.finish(&response, |_| Payload::unknown());
+        response
+    }
+
+    /// Like `send` but the caller finishes the metrics once it knows what came back
+    pub(crate) async fn send_measured(self) -> (Result<Response>, Measure) {
+        let intercept = match self.intercept {
+            Some(intercept) => intercept,
+            None => return (send(self.make, self.timeout, &self.retry).await, Measure(None)),
+        };
+        let response = match intercept.respond().await {
+            Ok(Some(response)) => Ok(response),
+            Ok(None) => intercept.record(send(self.make, self.timeout, &self.retry).await).await,
+            Err(error) => Err(error),
+        };
+        // failures are measured by the intercept itself
+        let measure = Measure(response.is_ok().then_some(intercept));
+        (response, measure)
+    }
+
+    /// Give up if the database does not respond within `duration`
+    pub fn timeout(mut self, duration: Duration) -> Self {
+        self.timeout = Some(duration);
+        self
+    }
+
+    /// Resend the query according to `policy` if it fails
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    pub fn execute(self) -> PendingRequest {
+        PendingRequest::new(async move {
+            let _ = self.send().await;
         })
     }
 
-    pub fn query(mut self, query: impl IntoQuery) -> Self {
-        self.0 = self.0.query(query);
+    pub(crate) fn kind(mut self, kind: RequestKind) -> Self {
+        if let Some(intercept) = &mut self.intercept {
+            intercept.kind(kind);
+        }
+        self
+    }
+
+    pub fn query(mut self, query: impl IntoQuery + Clone + 'static) -> Self {
+        if let Some(intercept) = &mut self.intercept {
+            intercept.query(query.clone());
+        }
+        let make = self.make;
+        self.make = Box::new(move || make().query(query.clone()));
         self
     }
 
-    pub fn bind(mut self, bindings: impl Serialize) -> Self {
-        self.0 = self.0.bind(bindings);
+    pub fn bind(mut self, bindings: impl Serialize + Clone + 'static) -> Self {
+        if let Some(intercept) = &mut self.intercept {
+            intercept.bind(bindings.clone());
+        }
+        let make = self.make;
+        self.make = Box::new(move || make().bind(bindings.clone()));
         self
     }
 
-    pub fn store_to<R: 'static + DeserializeOwned>(self, state: UseStateHandle<Option<Vec<R>>>) -> Suspension {
+    pub fn store_to<R: 'static + DeserializeOwned>(self, state: UseStateHandle<Option<Vec<R>>>) -> PendingRequest {
         self.store_index(state, 0)
     }
 
@@ -225,14 +784,18 @@ impl<C: Connection> SurrealQuery<C> {
         self,
         state: UseStateHandle<Option<Vec<R>>>,
         index: usize,
-    ) -> Suspension {
-        Suspension::from_future(async move {
-            match self.0.await {
-                Ok(mut response) => match response.take(index) {
-                    Ok(data) => state.set(Some(data)),
-                    Err(error) => handle_error(error),
-                },
-                Err(error) => handle_error(error),
+    ) -> PendingRequest {
+        PendingRequest::new(async move {
+            match self.send_measured().await {
+                (Ok(mut response), measure) => {
+                    let data = response.take::<Vec<R>>(index);
+                    measure.finish(&data, |data| Payload::count(data.len()));
+                    match data {
+                        Ok(data) => state.set(Some(data)),
+                        Err(error) => handle_error(error),
+                    }
+                }
+                (Err(error), _) => handle_error(error),
             }
         })
     }
@@ -240,56 +803,67 @@ impl<C: Connection> SurrealQuery<C> {
     pub fn store_multiple<R: 'static + DeserializeOwned>(
         self,
         states: impl IntoIterator<Item=(usize, UseStateHandle<Vec<R>>)> + 'static,
-    ) -> Suspension {
-        Suspension::from_future(async move {
-            match self.0.await {
-                Ok(mut response) => {
+    ) -> PendingRequest {
+        PendingRequest::new(async move {
+            match self.send_measured().await {
+                (Ok(mut response), measure) => {
+                    let mut rows = 0;
                     for (index, state) in states {
-                        match response.take(index) {
-                            Ok(data) => state.set(data),
+                        match response.take::<Vec<R>>(index) {
+                            Ok(data) => {
+                                rows += data.len();
+                                state.set(data)
+                            }
                             Err(error) => handle_error(error),
                         }
                     }
+                    measure.finish(&Ok::<_, SurrealError>(rows), |rows| Payload::count(*rows));
                 }
-                Err(error) => handle_error(error),
+                (Err(error), _) => handle_error(error),
             }
         })
     }
 
-    pub fn store_response<R: 'static + DeserializeOwned>(self, state: UseStateHandle<Response>) -> Suspension {
-        Suspension::from_future(async move {
-            match self.0.await {
+    pub fn store_response<R: 'static + DeserializeOwned>(self, state: UseStateHandle<Response>) -> PendingRequest {
+        PendingRequest::new(async move {
+            match self.send().await {
                 Ok(response) => state.set(response),
                 Err(error) => handle_error(error),
             }
         })
     }
 
-    pub fn then<F: 'static + FnOnce(Response) -> ()>(self, f: F) -> Suspension {
-        Suspension::from_future(async move {
-            self.0.await.ok().map(f);
+    pub fn then<F: 'static + FnOnce(Response) -> ()>(self, f: F) -> PendingRequest {
+        PendingRequest::new(async move {
+            self.send().await.ok().map(f);
         })
     }
 }
 
-#[async_trait]
+#[async_trait(?Send)]
 pub trait Fetch: Sized + 'static {
     type Target: Clone;
     async fn resolve(self) -> Result<Self::Target>;
-    
-    fn handle<F: 'static + FnOnce(Result<Self::Target>) -> ()>(self, f: F) -> Suspension {
-        Suspension::from_future(async move { f(self.resolve().await) })
+
+    /// Give up if the database does not respond within `duration`
+    fn timeout(self, duration: Duration) -> Self;
+
+    /// Resend the request according to `policy` if it fails
+    fn retry(self, policy: RetryPolicy) -> Self;
+
+    fn handle<F: 'static + FnOnce(Result<Self::Target>) -> ()>(self, f: F) -> PendingRequest {
+        PendingRequest::new(async move { f(self.resolve().await) })
     }
 
-    fn then<F: 'static + FnOnce(Self::Target) -> ()>(self, f: F) -> Suspension {
-        Suspension::from_future(async move {
+    fn then<F: 'static + FnOnce(Self::Target) -> ()>(self, f: F) -> PendingRequest {
+        PendingRequest::new(async move {
             self.resolve().await.ok().map(f);
         })
     }
 
-    fn store_to(self, state: &UseStateHandle<Option<Self::Target>>) -> Suspension {
+    fn store_to(self, state: &UseStateHandle<Option<Self::Target>>) -> PendingRequest {
         let state = state.clone();
-        Suspension::from_future(async move {
+        PendingRequest::new(async move {
             match self.resolve().await {
                 Ok(data) => state.set(Some(data)),
                 Err(error) => handle_error(error),
@@ -297,9 +871,9 @@ pub trait Fetch: Sized + 'static {
         })
     }
 
-    fn store_or_drop(self, state: &UseStateHandle<Option<Self::Target>>) -> Suspension {
+    fn store_or_drop(self, state: &UseStateHandle<Option<Self::Target>>) -> PendingRequest {
         let state = state.clone();
-        Suspension::from_future(async move {
+        PendingRequest::new(async move {
             match self.resolve().await {
                 Ok(data) => state.set(Some(data)),
                 Err(error) => handle_error(error),
@@ -307,8 +881,8 @@ pub trait Fetch: Sized + 'static {
         })
     }
 
-    fn append_to(self, result_list: QueryState<Self::Target>) -> Suspension {
-        Suspension::from_future(async move {
+    fn append_to(self, result_list: QueryState<Self::Target>) -> PendingRequest {
+        PendingRequest::new(async move {
             match self.resolve().await {
                 Ok(data) => result_list.append(data),
                 Err(error) => handle_error(error),
@@ -316,12 +890,12 @@ pub trait Fetch: Sized + 'static {
         })
     }
 
-    fn execute(self) -> Suspension {
-        Suspension::from_future(async move {
+    fn execute(self) -> PendingRequest {
+        PendingRequest::new(async move {
             match self.resolve().await {
                 Ok(_) => (),
                 Err(error) => handle_error(error),
             };
         })
     }
-}
\ No newline at end of file
+}
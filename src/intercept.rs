@@ -0,0 +1,285 @@
+use std::{cell::Cell, time::Duration};
+
+use serde::{de::DeserializeOwned, Serialize};
+use surrealdb::{
+    opt::{IntoQuery, Resource},
+    Response,
+};
+
+use crate::{
+    error::Result,
+    hooks::SurrealToken,
+    metrics::{Payload, QueryMetric, QueryMetrics},
+    traffic::{self, Recorded, RequestKind, Stopwatch, TrafficEvent, TrafficLog, TrafficPhase},
+};
+#[cfg(feature = "testing")]
+use crate::testing::{MockDatabase, TrafficRecorder};
+
+/// Follows a single request of a `SurrealToken` that has a mock, a recorder, a traffic log or metrics
+pub(crate) struct Intercept {
+    #[cfg(feature = "testing")]
+    mock: Option<MockDatabase>,
+    #[cfg(feature = "testing")]
+    recorder: Option<TrafficRecorder>,
+    traffic: Option<TrafficLog>,
+    metrics: Option<QueryMetrics>,
+    kind: RequestKind,
+    id: u64,
+    request: Recorded,
+    /// how many results the recorder takes from the response
+    #[cfg(feature = "testing")]
+    statements: usize,
+    started: Cell<Option<Stopwatch>>,
+}
+
+impl Intercept {
+    /// `None` if nothing is interested in the requests of `token`
+    pub(crate) fn new(token: &SurrealToken, kind: RequestKind, query: impl IntoQuery) -> Option<Self> {
+        let mut intercept = Self::empty(token, kind)?;
+        intercept.query(query);
+        Some(intercept)
+    }
+
+    /// For requests that are not sent as a query, e.g. `SELECT * FROM item` for `select(table)`
+    pub(crate) fn describe(
+        token: &SurrealToken,
+        kind: RequestKind,
+        verb: &str,
+        resource: surrealdb::Result<Resource>,
+    ) -> Option<Self> {
+        let mut intercept = Self::empty(token, kind)?;
+        intercept.request.statement = match resource {
+            Ok(Resource::Table(table)) => format!("{} {}", verb, table),
+            Ok(Resource::RecordId(thing)) => format!("{} {}", verb, thing),
+            _ => verb.to_owned(),
+        };
+        #[cfg(feature = "testing")]
+        {
+            intercept.statements = 1;
+        }
+        Some(intercept)
+    }
+
+    fn empty(token: &SurrealToken, kind: RequestKind) -> Option<Self> {
+        #[cfg(feature = "testing")]
+        let testing = token.mock.is_some() || token.recorder.is_some();
+        #[cfg(not(feature = "testing"))]
+        let testing = false;
+        if !testing && token.traffic.is_none() && token.metrics.is_none() {
+            return None;
+        }
+        Some(Self {
+            #[cfg(feature = "testing")]
+            mock: token.mock.clone(),
+            #[cfg(feature = "testing")]
+            recorder: token.recorder.clone(),
+            traffic: token.traffic.clone(),
+            metrics: token.metrics.clone(),
+            kind,
+            id: traffic::next_id(),
+            request: Recorded {
+                statement: String::new(),
+                bindings: Vec::new(),
+            },
+            #[cfg(feature = "testing")]
+            statements: 0,
+            started: Cell::new(None),
+        })
+    }
+
+    pub(crate) fn kind(&mut self, kind: RequestKind) {
+        self.kind = kind;
+    }
+
+    pub(crate) fn query(&mut self, query: impl IntoQuery) {
+        let (statement, _statements) = parse(query);
+        self.request.statement = match self.request.statement.is_empty() {
+            true => statement,
+            false => format!("{}; {}", self.request.statement, statement),
+        };
+        #[cfg(feature = "testing")]
+        {
+            self.statements += _statements;
+        }
+    }
+
+    pub(crate) fn bind(&mut self, bindings: impl Serialize) {
+        self.request
+            .bindings
+            .push(serde_json::to_value(bindings).unwrap_or_default());
+    }
+
+    /// Log the request and let the mock fail it, failures are measured right away
+    pub(crate) async fn check(&self) -> Result<()> {
+        self.started.set(Some(Stopwatch::start()));
+        self.log(TrafficPhase::Sent {
+            bindings: self.request.bindings.clone(),
+        });
+        #[cfg(feature = "testing")]
+        let result = match &self.mock {
+            Some(mock) => mock.check(&self.request).await,
+            None => Ok(()),
+        };
+        #[cfg(not(feature = "testing"))]
+        let result = Ok(());
+        if result.is_err() {
+            self.finish(&result);
+            self.measure(&result, |_| Payload::unknown());
+        }
+        result
+    }
+
+    /// Like `check` but answers the query from the replay of the mock if there is one
+    pub(crate) async fn respond(&self) -> Result<Option<Response>> {
+        self.check().await?;
+        #[cfg(feature = "testing")]
+        let response = match &self.mock {
+            Some(mock) => mock.replayed(&self.request).await,
+            None => Ok(None),
+        };
+        #[cfg(not(feature = "testing"))]
+        let response = Ok(None);
+        self.replayed(&response);
+        response
+    }
+
+    /// Like `respond` for requests that are answered with a single value instead of a `Response`, e.g. a `select`
+    pub(crate) async fn respond_value<T: DeserializeOwned>(&self) -> Result<Option<T>> {
+        self.check().await?;
+        #[cfg(feature = "testing")]
+        let response = match &self.mock {
+            Some(mock) => match mock.fixture_value(&self.request.statement) {
+                Some(fixture) => fixture.map(Some),
+                None => mock.replayed_value(&self.request),
+            },
+            None => Ok(None),
+        };
+        #[cfg(not(feature = "testing"))]
+        let response = Ok(None);
+        self.replayed(&response);
+        response
+    }
+
+    /// Log a replayed response, failures are measured right away
+    fn replayed<T>(&self, response: &Result<Option<T>>) {
+        match response {
+            Ok(None) => (),
+            Ok(Some(_)) => self.finish(&Ok(())),
+            Err(_) => {
+                self.finish(response);
+                self.measure(response, |_| Payload::unknown());
+            }
+        }
+    }
+
+    /// Log how the request went
+    pub(crate) fn finish<T>(&self, result: &Result<T>) {
+        let elapsed = self.elapsed();
+        self.log(match result {
+            Ok(_) => TrafficPhase::Received { elapsed },
+            Err(error) => TrafficPhase::Failed {
+                elapsed,
+                error: error.to_string(),
+            },
+        });
+    }
+
+    /// Finish a query and pass its response on, recording it on the way if there is a recorder
+    pub(crate) async fn record(&self, response: Result<Response>) -> Result<Response> {
+        self.finish(&response);
+        #[cfg(feature = "testing")]
+        let response = match &self.recorder {
+            Some(recorder) => recorder.record(self.request.clone(), response, self.statements).await,
+            None => response,
+        };
+        if response.is_err() {
+            self.measure(&response, |_| Payload::unknown());
+        }
+        response
+    }
+
+    /// Finish a request that is not sent as a query, recording its result if there is a recorder
+    pub(crate) fn record_value<T: Serialize>(&self, result: &Result<T>) {
+        self.finish(result);
+        #[cfg(feature = "testing")]
+        if let Some(recorder) = &self.recorder {
+            recorder.record_value(self.request.clone(), result);
+        }
+    }
+
+    /// Pass the metric of the request on, `payload` is only called if someone is interested in it
+    pub(crate) fn measure<T, E: ToString>(
+        &self,
+        result: &std::result::Result<T, E>,
+        payload: impl FnOnce(&T) -> Payload,
+    ) {
+        let metrics = match &self.metrics {
+            Some(metrics) => metrics,
+            None => return,
+        };
+        let (payload, error) = match result {
+            Ok(data) => (payload(data), None),
+            Err(error) => (Payload::unknown(), Some(error.to_string())),
+        };
+        let request_bytes = self.request.statement.len()
+            + self
+                .request
+                .bindings
+                .iter()
+                .map(|binding| binding.to_string().len())
+                .sum::<usize>();
+        metrics.record(QueryMetric {
+            kind: self.kind,
+            statement: self.request.statement.clone(),
+            latency: self.elapsed(),
+            request_bytes,
+            response_bytes: payload.bytes,
+            rows: payload.rows,
+            error,
+        });
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.started
+            .get()
+            .map(|started| started.elapsed())
+            .unwrap_or_default()
+    }
+
+    fn log(&self, phase: TrafficPhase) {
+        if let Some(traffic) = &self.traffic {
+            traffic.log(&TrafficEvent {
+                id: self.id,
+                kind: self.kind,
+                statement: self.request.statement.clone(),
+                phase,
+            });
+        }
+    }
+}
+
+/// The metrics of a query are only complete once the caller took its result from the response
+pub(crate) struct Measure(pub(crate) Option<Intercept>);
+
+impl Measure {
+    pub(crate) fn finish<T, E: ToString>(self, result: &std::result::Result<T, E>, payload: impl FnOnce(&T) -> Payload) {
+        if let Some(intercept) = self.0 {
+            intercept.measure(result, payload);
+        }
+    }
+}
+
+/// The normalized text of a query and the number of statements in it
+pub(crate) fn parse(query: impl IntoQuery) -> (String, usize) {
+    match query.into_query() {
+        Ok(statements) => (
+            statements
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("; "),
+            statements.len(),
+        ),
+        Err(_) => (String::new(), 0),
+    }
+}
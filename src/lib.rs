@@ -2,12 +2,37 @@
 pub mod props;
 pub mod hooks;
 pub mod components;
+pub mod cache;
+pub mod config;
+pub mod error;
+pub mod metrics;
+pub mod migrations;
+pub mod offline;
+pub mod query;
+pub mod schema;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod traffic;
+#[cfg(feature = "devtools")]
+pub mod devtools;
 pub use surreal_macros::*;
 
+// the macros refer to everything through `::syewreal`, so callers don't need these as dependencies themselves
+extern crate self as syewreal;
+#[doc(hidden)]
+pub use serde;
+pub use surrealdb;
+
+mod intercept;
 mod logging;
+mod retry;
+mod timeout;
+
+pub use timeout::TimedOut;
 
 pub use props::surreal_props::SurrealProps;
+pub use schema::SurrealSchema;
 
-pub type Client = surrealdb::Surreal<surrealdb::engine::remote::ws::Client>;
+pub type Client = surrealdb::Surreal<surrealdb::engine::any::Any>;
 pub type Login<'a> = surrealdb::opt::auth::Database<'a>;
 pub type RootLogin<'a> = surrealdb::opt::auth::Root<'a>;
\ No newline at end of file
@@ -18,11 +18,23 @@ pub fn handle_error(error: impl Error) {
 }
 
 
-/// prints an error to the JS console
+/// prints an error to the JS console, or to stderr on native targets
 #[inline]
 #[allow(dead_code)]
 pub fn print_error(error: impl Error) {
+    #[cfg(target_arch = "wasm32")]
     web_sys::console::error_1(&error.to_string().into());
+    #[cfg(not(target_arch = "wasm32"))]
+    eprintln!("{}", error);
+}
+
+/// prints a line to the JS console, or to stdout on native targets
+#[inline]
+pub fn print_message(message: &str) {
+    #[cfg(target_arch = "wasm32")]
+    web_sys::console::log_1(&message.into());
+    #[cfg(not(target_arch = "wasm32"))]
+    println!("{}", message);
 }
 
 /// always panics with a given error
@@ -38,7 +50,17 @@ pub enum Operation {
 }
 
 
-#[cfg(feature = "log_traffic")]
+impl Operation {
+    #[allow(dead_code)]
+    fn describe(&self) -> &'static str {
+        match self {
+            Operation::Update => "Sent Update",
+            Operation::Receive => "Received"
+        }
+    }
+}
+
+#[cfg(all(feature = "log_traffic", target_arch = "wasm32"))]
 /// prints an object to the JS console if the `log_traffic` feature is enabled
 pub fn print_traffic(direction: Operation, obj: &impl Serialize) {
     use js_sys::JSON::parse;
@@ -47,13 +69,13 @@ pub fn print_traffic(direction: Operation, obj: &impl Serialize) {
         Ok(value) => value,
         Err(value) => value
     };
-    let direction = match direction {
-        Operation::Update => "Sent Update",
-        Operation::Receive => "Received"
-    };
-    web_sys::console::log_2(&direction.into(), &obj);
+    web_sys::console::log_2(&direction.describe().into(), &obj);
+}
 
-    
+#[cfg(all(feature = "log_traffic", not(target_arch = "wasm32")))]
+/// prints an object to stdout if the `log_traffic` feature is enabled
+pub fn print_traffic(direction: Operation, obj: &impl Serialize) {
+    println!("{} {}", direction.describe(), serde_json::to_string(&obj).unwrap());
 }
 #[cfg(not(feature = "log_traffic"))]
 #[inline]
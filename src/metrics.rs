@@ -0,0 +1,226 @@
+//! Latency, payload size and row count of every request sent through a `SurrealToken`
+//!
+//! Pass a `QueryMetrics` to `<SurrealContext/>`, read the per statement summaries with `use_query_metrics`
+//! or forward every single measurement to a `MetricsSink`
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc, time::Duration};
+
+use serde::Serialize;
+use yew::{hook, use_effect_with_deps, use_force_update, Callback};
+
+use crate::{hooks::use_surreal, traffic::RequestKind};
+
+/// The measurement of a single request
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QueryMetric {
+    pub kind: RequestKind,
+    pub statement: String,
+    /// From sending the request until the response was deserialized
+    pub latency: Duration,
+    /// Size of the statement and the JSON encoded bindings
+    pub request_bytes: usize,
+    /// Size of the JSON encoded records that came back, `None` if they are not serializable
+    pub response_bytes: Option<usize>,
+    /// `None` if the response was passed on without looking at it, e.g. by `SurrealQuery::then`
+    pub rows: Option<usize>,
+    /// Why the request failed
+    pub error: Option<String>,
+}
+
+/// A sink for `QueryMetric`s, e.g. to forward them to a telemetry service
+pub trait MetricsSink {
+    fn record(&self, metric: &QueryMetric);
+}
+
+impl<T: MetricsSink> MetricsSink for Rc<T> {
+    fn record(&self, metric: &QueryMetric) {
+        (**self).record(metric)
+    }
+}
+
+impl MetricsSink for Callback<QueryMetric> {
+    fn record(&self, metric: &QueryMetric) {
+        self.emit(metric.clone())
+    }
+}
+
+/// Everything measured for one statement
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricsSummary {
+    pub kind: RequestKind,
+    pub statement: String,
+    pub count: usize,
+    pub errors: usize,
+    pub total_latency: Duration,
+    pub max_latency: Duration,
+    /// Sum of the known row counts
+    pub rows: usize,
+    /// Sum of the known response sizes
+    pub response_bytes: usize,
+    pub last: QueryMetric,
+}
+
+impl MetricsSummary {
+    fn new(metric: &QueryMetric) -> Self {
+        Self {
+            kind: metric.kind,
+            statement: metric.statement.clone(),
+            count: 0,
+            errors: 0,
+            total_latency: Duration::ZERO,
+            max_latency: Duration::ZERO,
+            rows: 0,
+            response_bytes: 0,
+            last: metric.clone(),
+        }
+    }
+
+    fn add(&mut self, metric: &QueryMetric) {
+        self.count += 1;
+        self.errors += metric.error.is_some() as usize;
+        self.total_latency += metric.latency;
+        self.max_latency = self.max_latency.max(metric.latency);
+        self.rows += metric.rows.unwrap_or_default();
+        self.response_bytes += metric.response_bytes.unwrap_or_default();
+        self.last = metric.clone();
+    }
+
+    pub fn average_latency(&self) -> Duration {
+        match self.count {
+            0 => Duration::ZERO,
+            count => self.total_latency / count as u32,
+        }
+    }
+}
+
+struct Inner {
+    sinks: RefCell<Vec<Rc<dyn MetricsSink>>>,
+    summaries: RefCell<BTreeMap<String, MetricsSummary>>,
+}
+
+/// Collects the metrics of a `<SurrealContext/>` and passes them on to its sinks
+///
+/// Create it once (e.g. with `use_memo`) and pass it to `<SurrealContext/>`
+#[derive(Clone)]
+pub struct QueryMetrics(Rc<Inner>);
+
+impl PartialEq for QueryMetrics {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Default for QueryMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QueryMetrics {
+    pub fn new() -> Self {
+        Self(Rc::new(Inner {
+            sinks: RefCell::new(Vec::new()),
+            summaries: RefCell::new(BTreeMap::new()),
+        }))
+    }
+
+    /// Pass every metric to `sink` as well
+    pub fn with(self, sink: impl MetricsSink + 'static) -> Self {
+        self.add(sink);
+        self
+    }
+
+    pub fn add(&self, sink: impl MetricsSink + 'static) {
+        self.0.sinks.borrow_mut().push(Rc::new(sink));
+    }
+
+    pub(crate) fn add_shared(&self, sink: Rc<dyn MetricsSink>) {
+        self.0.sinks.borrow_mut().push(sink);
+    }
+
+    pub(crate) fn remove_shared(&self, sink: &Rc<dyn MetricsSink>) {
+        self.0.sinks.borrow_mut().retain(|other| !Rc::ptr_eq(other, sink));
+    }
+
+    /// The summaries of all statements, slowest on average first
+    pub fn summaries(&self) -> Vec<MetricsSummary> {
+        let mut summaries = self.0.summaries.borrow().values().cloned().collect::<Vec<_>>();
+        summaries.sort_by(|a, b| b.average_latency().cmp(&a.average_latency()));
+        summaries
+    }
+
+    /// Forget the summaries, the sinks are kept
+    pub fn reset(&self) {
+        self.0.summaries.borrow_mut().clear();
+    }
+
+    pub(crate) fn record(&self, metric: QueryMetric) {
+        self.0
+            .summaries
+            .borrow_mut()
+            .entry(metric.statement.clone())
+            .or_insert_with(|| MetricsSummary::new(&metric))
+            .add(&metric);
+        // a sink may change the sinks while it is called
+        let sinks = self.0.sinks.borrow().clone();
+        for sink in sinks {
+            sink.record(&metric);
+        }
+    }
+}
+
+/// What came back for a request, as far as it is known where the response is taken apart
+pub(crate) struct Payload {
+    pub rows: Option<usize>,
+    pub bytes: Option<usize>,
+}
+
+impl Payload {
+    pub(crate) fn of<'a, T: Serialize + 'a>(rows: impl IntoIterator<Item = &'a T>) -> Self {
+        let (rows, bytes) = rows.into_iter().fold((0, 0), |(rows, bytes), row| {
+            (rows + 1, bytes + serde_json::to_vec(row).map(|row| row.len()).unwrap_or_default())
+        });
+        Self {
+            rows: Some(rows),
+            bytes: Some(bytes),
+        }
+    }
+
+    /// For records that can't be serialized again
+    pub(crate) fn count(rows: usize) -> Self {
+        Self {
+            rows: Some(rows),
+            bytes: None,
+        }
+    }
+
+    pub(crate) fn unknown() -> Self {
+        Self { rows: None, bytes: None }
+    }
+}
+
+/// The summaries of every statement sent through the surrounding `<SurrealContext/>`, slowest on average first
+///
+/// The component is rendered again whenever a request finished, the list is empty if the context has no `QueryMetrics`
+#[hook]
+pub fn use_query_metrics() -> Vec<MetricsSummary> {
+    let token = use_surreal();
+    let update = use_force_update();
+
+    use_effect_with_deps(
+        move |metrics| {
+            let sink = metrics.clone().map(|metrics| {
+                let sink: Rc<dyn MetricsSink> = Rc::new(Callback::from(move |_: QueryMetric| update.force_update()));
+                metrics.add_shared(sink.clone());
+                (metrics, sink)
+            });
+            move || {
+                if let Some((metrics, sink)) = sink {
+                    metrics.remove_shared(&sink);
+                }
+            }
+        },
+        token.metrics.clone(),
+    );
+
+    token.metrics.as_ref().map(QueryMetrics::summaries).unwrap_or_default()
+}
@@ -0,0 +1,138 @@
+//! Versioned schema changes that are applied whenever a token connects
+//!
+//! Embed a directory of `<version>_<name>.surql` files with `embed_migrations!("migrations")` and pass it to
+//! `use_surreal_login_with_migrations`. The versions that were applied are stored in a table (`migration` by default),
+//! each migration runs in its own transaction
+use std::rc::Rc;
+
+use serde::Deserialize;
+use surrealdb::sql::Value;
+
+use crate::{error::SurrealError, hooks::SignInError, hooks::SurrealToken, intercept};
+
+/// A single `.surql` file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Migration {
+    pub version: u64,
+    pub name: &'static str,
+    pub statements: &'static str,
+}
+
+/// A row of the table the applied versions are stored in
+#[derive(Deserialize)]
+struct Applied {
+    version: u64,
+}
+
+/// How far the migrations of a token are
+#[derive(Debug, Clone, PartialEq)]
+pub enum MigrationState {
+    /// No migration was checked yet
+    Idle,
+    /// `version` is applied right now, `done` of the `total` pending migrations are finished
+    Running {
+        version: u64,
+        name: &'static str,
+        done: usize,
+        total: usize,
+    },
+    /// Every migration is applied
+    Done,
+    /// `version` and all later migrations were not applied, `None` if the applied versions could not be read
+    Failed { version: Option<u64>, error: String },
+}
+
+/// The migrations of an app in order of their version
+#[derive(Debug, Clone, PartialEq)]
+pub struct Migrations {
+    migrations: Rc<Vec<Migration>>,
+    table: &'static str,
+}
+
+impl Migrations {
+    pub fn new(mut migrations: Vec<Migration>) -> Self {
+        migrations.sort_by_key(|migration| migration.version);
+        Self {
+            migrations: Rc::new(migrations),
+            table: "migration",
+        }
+    }
+
+    /// Store the applied versions in `table` instead of `migration`
+    pub fn table(mut self, table: &'static str) -> Self {
+        self.table = table;
+        self
+    }
+
+    pub fn list(&self) -> &[Migration] {
+        &self.migrations
+    }
+
+    /// Apply every migration that was not applied yet and report the progress to `token.migration_state`
+    ///
+    /// Tokens from `use_surreal_login_with_migrations` do this when they sign in, call it yourself for a token that
+    /// can't sign in, e.g. one from `use_surreal_connected` on the server
+    pub async fn run(&self, token: &SurrealToken) -> Result<(), SignInError> {
+        let applied = self.applied(token).await.map_err(|error| {
+            let error = error.to_string();
+            token.migration_state.set(MigrationState::Failed {
+                version: None,
+                error: error.clone(),
+            });
+            SignInError::Migration(None, error)
+        })?;
+        let pending = self
+            .migrations
+            .iter()
+            .filter(|migration| !applied.contains(&migration.version))
+            .collect::<Vec<_>>();
+
+        for (done, migration) in pending.iter().enumerate() {
+            token.migration_state.set(MigrationState::Running {
+                version: migration.version,
+                name: migration.name,
+                done,
+                total: pending.len(),
+            });
+            if let Err(error) = self.apply(token, migration).await {
+                let error = error.to_string();
+                token.migration_state.set(MigrationState::Failed {
+                    version: Some(migration.version),
+                    error: error.clone(),
+                });
+                return Err(SignInError::Migration(Some(migration.version), error));
+            }
+        }
+        token.migration_state.set(MigrationState::Done);
+        Ok(())
+    }
+
+    async fn applied(&self, token: &SurrealToken) -> Result<Vec<u64>, SurrealError> {
+        // a strict database doesn't know the table before the first migration
+        let query = format!("DEFINE TABLE {};\nSELECT version FROM type::table($table)", self.table);
+        let mut response = token.query(query).bind(("table", self.table)).send().await?;
+        response.take::<Value>(0)?;
+        let applied = response.take::<Vec<Applied>>(1)?;
+        Ok(applied.into_iter().map(|applied| applied.version).collect())
+    }
+
+    async fn apply(&self, token: &SurrealToken, migration: &Migration) -> Result<(), SurrealError> {
+        let query = format!(
+            "BEGIN TRANSACTION;\n{};\nCREATE type::thing($table, $version) SET version = $version, name = $name, applied = time::now();\nCOMMIT TRANSACTION;",
+            migration.statements.trim().trim_end_matches(';')
+        );
+        // BEGIN and COMMIT don't have a result
+        let (_, statements) = intercept::parse(query.clone());
+        let mut response = token
+            .query(query)
+            .bind(("table", self.table))
+            .bind(("version", migration.version))
+            .bind(("name", migration.name))
+            .send()
+            .await?;
+        for index in 0..statements.saturating_sub(2) {
+            response.take::<Value>(index)?;
+        }
+        Ok(())
+    }
+}
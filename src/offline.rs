@@ -0,0 +1,443 @@
+use std::{
+    cell::{Cell, RefCell},
+    future::Future,
+    rc::Rc,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use surrealdb::{
+    engine::any::Any as Client,
+    sql::{thing, Thing},
+};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::{JsCast, JsValue};
+use yew::{
+    platform::{spawn_local, time::sleep},
+    Callback,
+};
+
+use crate::{
+    error::{Result, SurrealError},
+    hooks::{PendingRequest, SurrealQuery, SurrealToken},
+    logging::handle_error,
+    traffic::RequestKind,
+    CREATE, DELETE, UPDATE,
+};
+
+/// How long to wait before the first replay after the database could not be reached
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+/// The wait time doubles after every failed replay up to this
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// A create, update or delete that could not be sent yet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Mutation {
+    /// `CREATE what CONTENT data`, `what` is a table name or a record id
+    Create { what: String, data: Value },
+    /// `UPDATE thing MERGE data`
+    Update { thing: String, data: Value },
+    /// `DELETE thing`
+    Delete { thing: String },
+}
+
+impl Mutation {
+    /// The statement to replay, the targets are read back from the storage and therefore only ever bound as params
+    ///
+    /// Like every mutation it is only sent once unless the retry policy of the token includes mutations
+    fn send(&self, token: &SurrealToken) -> Result<SurrealQuery<Client>> {
+        let mut token = token.clone();
+        token.retry = token.retry.for_mutation();
+        let query = match self {
+            Mutation::Create { what, data } => match thing(what) {
+                Ok(id) => token.query(CREATE!($what CONTENT $data)).bind(("what", id)),
+                Err(_) => token
+                    .query(CREATE!(type::table($what) CONTENT $data))
+                    .bind(("what", what.clone())),
+            }
+            .bind(("data", data.clone()))
+            .kind(RequestKind::Create),
+            Mutation::Update { thing, data } => token
+                .query(UPDATE!($thing MERGE $data RETURN NONE))
+                .bind(("thing", Self::parse_thing(thing)?))
+                .bind(("data", data.clone()))
+                .kind(RequestKind::Update),
+            Mutation::Delete { thing } => token
+                .query(DELETE!($thing))
+                .bind(("thing", Self::parse_thing(thing)?))
+                .kind(RequestKind::Delete),
+        };
+        Ok(query)
+    }
+
+    fn parse_thing(raw: &str) -> Result<Thing> {
+        thing(raw).map_err(|error| SurrealError::Database(surrealdb::Error::Db(error)))
+    }
+}
+
+/// A queued mutation that the database rejected during the replay
+///
+/// A replay that timed out is reported as well, it is not sent again as it may already have been applied
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub mutation: Mutation,
+    pub error: Rc<SurrealError>,
+}
+
+/// Where the `OfflineQueue` keeps its mutations while the app is offline
+pub trait MutationStorage {
+    fn load(&self) -> Vec<Mutation>;
+    fn store(&self, mutations: &[Mutation]);
+}
+
+/// Keeps the mutations in `window.localStorage` so they survive a reload
+pub struct LocalStorage {
+    key: String,
+}
+
+impl LocalStorage {
+    pub fn new(key: impl Into<String>) -> Self {
+        Self { key: key.into() }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    /// There is no `localStorage` outside of the browser
+    #[cfg(not(target_arch = "wasm32"))]
+    fn storage() -> Option<web_sys::Storage> {
+        None
+    }
+}
+
+impl MutationStorage for LocalStorage {
+    fn load(&self) -> Vec<Mutation> {
+        Self::storage()
+            .and_then(|storage| storage.get_item(&self.key).ok()?)
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default()
+    }
+
+    fn store(&self, mutations: &[Mutation]) {
+        if let (Some(storage), Ok(raw)) = (Self::storage(), serde_json::to_string(mutations)) {
+            let _ = storage.set_item(&self.key, &raw);
+        }
+    }
+}
+
+/// Keeps the mutations in memory, they are lost on reload
+#[derive(Default)]
+pub struct MemoryStorage(RefCell<Vec<Mutation>>);
+
+impl MutationStorage for MemoryStorage {
+    fn load(&self) -> Vec<Mutation> {
+        self.0.borrow().clone()
+    }
+
+    fn store(&self, mutations: &[Mutation]) {
+        *self.0.borrow_mut() = mutations.to_vec();
+    }
+}
+
+/// Keeps the mutations in IndexedDB so they survive a reload
+///
+/// IndexedDB can only be read asynchronously, `open` loads the stored mutations once and afterwards every change is written through
+#[cfg(target_arch = "wasm32")]
+pub struct IndexedDbStorage {
+    database: web_sys::IdbDatabase,
+    mutations: RefCell<Vec<Mutation>>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl IndexedDbStorage {
+    const STORE: &'static str = "mutations";
+    const KEY: &'static str = "pending";
+
+    /// Open (or create) the IndexedDB database `name` and load the mutations stored in it
+    pub async fn open(name: &str) -> std::result::Result<Self, JsValue> {
+        let factory = web_sys::window()
+            .ok_or("there is no window")?
+            .indexed_db()?
+            .ok_or("IndexedDB is not available")?;
+        let request = factory.open_with_u32(name, 1)?;
+        let opened = request.clone();
+        let _upgrade = gloo_events::EventListener::once(&request, "upgradeneeded", move |_| {
+            let database = opened.result().ok().and_then(|result| result.dyn_into::<web_sys::IdbDatabase>().ok());
+            if let Some(database) = database {
+                let _ = database.create_object_store(Self::STORE);
+            }
+        });
+        let database: web_sys::IdbDatabase = wait(&request).await?.dyn_into()?;
+        let store = database.transaction_with_str(Self::STORE)?.object_store(Self::STORE)?;
+        let raw = wait(&store.get(&JsValue::from_str(Self::KEY))?).await?;
+        let mutations = raw
+            .as_string()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        Ok(Self {
+            database,
+            mutations: RefCell::new(mutations),
+        })
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl MutationStorage for IndexedDbStorage {
+    fn load(&self) -> Vec<Mutation> {
+        self.mutations.borrow().clone()
+    }
+
+    fn store(&self, mutations: &[Mutation]) {
+        *self.mutations.borrow_mut() = mutations.to_vec();
+        if let Ok(raw) = serde_json::to_string(mutations) {
+            // transactions on the same store are applied in the order they were created
+            let _ = self
+                .database
+                .transaction_with_str_and_mode(Self::STORE, web_sys::IdbTransactionMode::Readwrite)
+                .and_then(|transaction| transaction.object_store(Self::STORE))
+                .and_then(|store| store.put_with_key(&JsValue::from_str(&raw), &JsValue::from_str(Self::KEY)));
+        }
+    }
+}
+
+/// Wait until `request` is done and return its result
+#[cfg(target_arch = "wasm32")]
+async fn wait(request: &web_sys::IdbRequest) -> std::result::Result<JsValue, JsValue> {
+    let (sender, receiver) = futures::channel::oneshot::channel();
+    let sender = Rc::new(RefCell::new(Some(sender)));
+    let listen = |event: &'static str, succeeded: bool| {
+        let sender = sender.clone();
+        gloo_events::EventListener::once(request, event, move |_| {
+            if let Some(sender) = sender.borrow_mut().take() {
+                let _ = sender.send(succeeded);
+            }
+        })
+    };
+    let _listeners = (listen("success", true), listen("error", false));
+    match receiver.await {
+        Ok(true) => request.result(),
+        _ => Err(JsValue::from_str("the IndexedDB request failed")),
+    }
+}
+
+struct Inner {
+    storage: Box<dyn MutationStorage>,
+    pending: RefCell<Vec<Mutation>>,
+    on_conflict: RefCell<Option<Callback<Conflict>>>,
+    replaying: Cell<bool>,
+    /// set once a mutation failed because the database could not be reached, cleared when the queue was replayed
+    offline: Cell<bool>,
+    /// whether a replay is already waiting for its backoff
+    scheduled: Cell<bool>,
+    backoff: Cell<Duration>,
+}
+
+/// Collects the mutations that were issued while the database could not be reached and replays them in order
+///
+/// Create it once (e.g. with `use_memo`) and pass it to `<SurrealContext/>`
+#[derive(Clone)]
+pub struct OfflineQueue(Rc<Inner>);
+
+impl PartialEq for OfflineQueue {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl OfflineQueue {
+    pub fn new(storage: impl MutationStorage + 'static) -> Self {
+        Self(Rc::new(Inner {
+            pending: RefCell::new(storage.load()),
+            storage: Box::new(storage),
+            on_conflict: RefCell::new(None),
+            replaying: Cell::new(false),
+            offline: Cell::new(false),
+            scheduled: Cell::new(false),
+            backoff: Cell::new(MIN_BACKOFF),
+        }))
+    }
+
+    /// A queue stored in `window.localStorage` under `key`
+    pub fn local_storage(key: impl Into<String>) -> Self {
+        Self::new(LocalStorage::new(key))
+    }
+
+    /// A queue stored in the IndexedDB database `name`
+    #[cfg(target_arch = "wasm32")]
+    pub async fn indexed_db(name: &str) -> std::result::Result<Self, JsValue> {
+        Ok(Self::new(IndexedDbStorage::open(name).await?))
+    }
+
+    /// Called for every queued mutation the database rejects during the replay, the mutation is dropped afterwards
+    pub fn on_conflict(self, callback: Callback<Conflict>) -> Self {
+        *self.0.on_conflict.borrow_mut() = Some(callback);
+        self
+    }
+
+    pub fn pending(&self) -> Vec<Mutation> {
+        self.0.pending.borrow().clone()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.pending.borrow().is_empty()
+    }
+
+    /// Whether the last attempt to send a mutation failed because the database could not be reached
+    pub fn is_offline(&self) -> bool {
+        self.0.offline.get()
+    }
+
+    pub(crate) fn push(&self, mutation: Mutation) {
+        let mut pending = self.0.pending.borrow_mut();
+        pending.push(mutation);
+        self.0.storage.store(&pending);
+    }
+
+    fn front(&self) -> Option<Mutation> {
+        self.0.pending.borrow().first().cloned()
+    }
+
+    fn pop_front(&self) {
+        let mut pending = self.0.pending.borrow_mut();
+        if !pending.is_empty() {
+            pending.remove(0);
+        }
+        self.0.storage.store(&pending);
+    }
+
+    /// Send all queued mutations in order, stops at the first one that fails because the database can't be reached
+    ///
+    /// All `QueryState`s of the token are refetched afterwards to replace the optimistic data.
+    /// If the database could not be reached the replay is tried again after a growing backoff
+    pub fn replay(&self, token: &SurrealToken) -> PendingRequest {
+        let queue = self.clone();
+        let token = token.clone();
+        PendingRequest::new(async move {
+            if queue.0.replaying.replace(true) {
+                return;
+            }
+            let mut replayed = false;
+            let mut offline = false;
+            while let Some(mutation) = queue.front() {
+                let result = match mutation.send(&token) {
+                    Ok(request) => match request.send().await {
+                        Ok(mut response) => response.take::<Vec<Value>>(0).map_err(SurrealError::from),
+                        Err(error) => Err(error),
+                    },
+                    Err(error) => Err(error),
+                };
+                match result {
+                    Ok(_) => (),
+                    Err(error) if error.is_offline() => {
+                        offline = true;
+                        break;
+                    }
+                    Err(error) => {
+                        let callback = queue.0.on_conflict.borrow().clone();
+                        match callback {
+                            Some(callback) => callback.emit(Conflict {
+                                mutation,
+                                error: Rc::new(error),
+                            }),
+                            None => handle_error(error),
+                        }
+                    }
+                }
+                queue.pop_front();
+                replayed = true;
+            }
+            queue.0.replaying.set(false);
+            match offline {
+                true => queue.went_offline(&token),
+                false => {
+                    queue.0.offline.set(false);
+                    queue.0.backoff.set(MIN_BACKOFF);
+                }
+            }
+            if replayed {
+                token.refetch();
+            }
+        })
+    }
+
+    /// Remember that the database can't be reached and replay the queue once the backoff passed
+    fn went_offline(&self, token: &SurrealToken) {
+        self.0.offline.set(true);
+        if self.0.scheduled.replace(true) {
+            return;
+        }
+        let delay = self.0.backoff.get();
+        self.0.backoff.set(delay.saturating_mul(2).min(MAX_BACKOFF));
+        let queue = self.clone();
+        let token = token.clone();
+        spawn_local(async move {
+            sleep(delay).await;
+            queue.0.scheduled.set(false);
+            queue.replay(&token);
+        });
+    }
+}
+
+/// A mutation ready to be queued together with the local data to show until it was replayed
+pub(crate) struct Queued<T> {
+    pub queue: OfflineQueue,
+    pub token: SurrealToken,
+    pub mutation: Mutation,
+    pub optimistic: Option<T>,
+}
+
+impl<T> Queued<T> {
+    /// Queue the mutation behind the pending ones and show the local data until it was replayed
+    fn enqueue(self, f: impl FnOnce(Result<T>)) -> (OfflineQueue, SurrealToken) {
+        self.queue.push(self.mutation);
+        if let Some(data) = self.optimistic {
+            f(Ok(data));
+        }
+        (self.queue, self.token)
+    }
+}
+
+/// Send `request` and pass the result to `f`, queue it instead if the database can't be reached
+///
+/// While mutations are queued the request is queued behind them so it is not overwritten by older ones,
+/// the queue is replayed right away unless it is offline. Only connection errors queue the request,
+/// a timeout is passed to `f` as the request may already have been applied
+pub(crate) async fn send_or_queue_with<T>(
+    request: impl Future<Output = Result<T>>,
+    queued: Option<Queued<T>>,
+    f: impl FnOnce(Result<T>),
+) {
+    match queued {
+        Some(queued) if queued.queue.is_offline() => {
+            queued.enqueue(f);
+        }
+        Some(queued) if !queued.queue.is_empty() => {
+            let (queue, token) = queued.enqueue(f);
+            queue.replay(&token);
+        }
+        Some(queued) => match request.await {
+            Err(error) if error.is_offline() => {
+                let (queue, token) = queued.enqueue(f);
+                queue.went_offline(&token);
+            }
+            result => f(result),
+        },
+        None => f(request.await),
+    }
+}
+
+/// Like `send_or_queue_with` but errors are handled by `handle_error`
+pub(crate) async fn send_or_queue<T>(
+    request: impl Future<Output = Result<T>>,
+    queued: Option<Queued<T>>,
+    f: impl FnOnce(T),
+) {
+    send_or_queue_with(request, queued, |result| match result {
+        Ok(data) => f(data),
+        Err(error) => handle_error(error),
+    })
+    .await
+}
@@ -1,10 +1,72 @@
-use surrealdb::{sql::{statements::SelectStatement, Statement, Query, Thing, Values}, opt::IntoQuery};
+use std::{fmt::Debug, rc::Rc};
+
+use serde::Serialize;
+use surrealdb::{engine::any::Any, sql::{statements::SelectStatement, Statement, Query, Thing, Values}, opt::IntoQuery};
 use yew::{html::IntoPropValue, AttrValue};
 
+use crate::{hooks::SurrealQuery, query::BoundQuery};
+
 
 #[derive(Clone, PartialEq, Debug)]
 pub struct Selector {
     pub base: Option<SelectStatement>,
+    /// The values of the `$params` of `base`, e.g. from `bound_sqlx!`
+    pub bindings: Bindings,
+}
+
+impl Selector {
+    /// Bind the values of the `$params` to `query`
+    pub fn bind(&self, query: SurrealQuery<Any>) -> SurrealQuery<Any> {
+        match &self.bindings.bind {
+            Some(bind) => bind(query),
+            None => query,
+        }
+    }
+}
+
+/// The values bound to the `$params` of a `Selector`, compared by their JSON
+#[derive(Clone, Default)]
+pub struct Bindings {
+    json: serde_json::Value,
+    bind: Option<Rc<dyn Fn(SurrealQuery<Any>) -> SurrealQuery<Any>>>,
+}
+
+impl Bindings {
+    pub fn new(bindings: impl Serialize + Clone + 'static) -> Self {
+        Self {
+            json: serde_json::to_value(&bindings).unwrap_or_default(),
+            bind: Some(Rc::new(move |query| query.bind(bindings.clone()))),
+        }
+    }
+
+    /// Bindings that are applied by `bind` and shown as `json`
+    pub(crate) fn from_fn(json: serde_json::Value, bind: impl Fn(SurrealQuery<Any>) -> SurrealQuery<Any> + 'static) -> Self {
+        Self {
+            json,
+            bind: Some(Rc::new(bind)),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bind.is_none()
+    }
+
+    /// The bound values as JSON, record ids are written as objects
+    pub fn json(&self) -> &serde_json::Value {
+        &self.json
+    }
+}
+
+impl PartialEq for Bindings {
+    fn eq(&self, other: &Self) -> bool {
+        self.json == other.json
+    }
+}
+
+impl Debug for Bindings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.json.fmt(f)
+    }
 }
 
 impl TryFrom<Selector> for SelectStatement {
@@ -21,6 +83,7 @@ impl From<Vec<Statement>> for Selector {
                 Some(Statement::Select(x)) => Some(x.clone()),
                 _ => None,
             },
+            bindings: Bindings::default(),
         }
     }
 }
@@ -46,6 +109,15 @@ impl IntoPropValue<Selector> for Query {
     }
 }
 
+impl<P: Serialize + Clone + 'static> IntoPropValue<Selector> for BoundQuery<P> {
+    fn into_prop_value(self) -> Selector {
+        Selector {
+            bindings: Bindings::new(self.bindings),
+            ..self.query.into_prop_value()
+        }
+    }
+}
+
 impl IntoPropValue<Selector> for &str {
     fn into_prop_value(self) -> Selector {
         self.into_query().unwrap().into()
@@ -64,8 +136,9 @@ impl IntoPropValue<Selector> for Thing {
             base: Some(SelectStatement{
                 what: Values(vec![self.into()]),
                 ..Default::default()
-            })
-        }        
+            }),
+            bindings: Bindings::default(),
+        }
     }
 }
 
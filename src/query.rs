@@ -0,0 +1,313 @@
+//! Queries that are checked at compile time, either with `sqlx!`, `bound_sqlx!` or with a `SelectBuilder` generated by
+//! the `SurrealProps` derive
+use std::{fmt::Display, marker::PhantomData};
+
+use serde::Serialize;
+use surrealdb::sql::{parse, Query, Thing, Value};
+use yew::{html::IntoPropValue, AttrValue};
+
+use crate::{
+    error::{Result, SurrealError},
+    props::{
+        id::ID,
+        selector::{Bindings, Selector},
+    },
+};
+
+/// A query together with a value for each of its `$params`
+///
+/// Built by `bound_sqlx!`, the bindings are a struct with one field per param. Send it with `SurrealToken::bound_query`
+#[derive(Clone, Debug)]
+pub struct BoundQuery<P: Serialize> {
+    pub query: Query,
+    pub bindings: P,
+}
+
+impl<P: Serialize> BoundQuery<P> {
+    pub fn new(query: Query, bindings: P) -> Self {
+        Self { query, bindings }
+    }
+}
+
+/// A value that can be compared with a field, it is bound as a `$param` and never written into the query
+pub trait QueryValue {
+    fn to_value(&self) -> Value;
+}
+
+macro_rules! into_value {
+    ($($ty:ty),*) => {
+        $(impl QueryValue for $ty {
+            fn to_value(&self) -> Value {
+                Value::from(self.clone())
+            }
+        })*
+    };
+}
+
+into_value!(bool, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64, String);
+
+impl QueryValue for str {
+    fn to_value(&self) -> Value {
+        Value::from(self)
+    }
+}
+
+impl QueryValue for AttrValue {
+    fn to_value(&self) -> Value {
+        Value::from(self.as_str())
+    }
+}
+
+impl QueryValue for Thing {
+    fn to_value(&self) -> Value {
+        Value::Thing(self.clone())
+    }
+}
+
+impl QueryValue for ID {
+    fn to_value(&self) -> Value {
+        Value::Thing((**self).clone())
+    }
+}
+
+impl<T: QueryValue> QueryValue for Option<T> {
+    fn to_value(&self) -> Value {
+        match self {
+            Some(value) => value.to_value(),
+            None => Value::None,
+        }
+    }
+}
+
+impl<T: QueryValue> QueryValue for Vec<T> {
+    fn to_value(&self) -> Value {
+        Value::Array(self.iter().map(QueryValue::to_value).collect::<Vec<_>>().into())
+    }
+}
+
+/// A field of a `SurrealProps` struct, generated as part of `...Fields` by the derive
+pub struct Field<T> {
+    name: &'static str,
+    ty: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Field<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Field<T> {}
+
+impl<T> Field<T> {
+    pub const fn new(name: &'static str) -> Self {
+        Self { name, ty: PhantomData }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn compare(&self, operator: &str, value: &dyn QueryValue) -> Condition {
+        Condition(vec![
+            Part::Sql(format!("{} {} ", self.name, operator)),
+            Part::Value(value.to_value()),
+        ])
+    }
+
+    pub fn is_none(&self) -> Condition {
+        Condition(vec![Part::Sql(format!("{} = NONE", self.name))])
+    }
+}
+
+// comparisons build a `Condition`, they have nothing to do with `PartialEq` and `PartialOrd`
+#[allow(clippy::should_implement_trait)]
+impl<T: QueryValue> Field<T> {
+    pub fn eq(&self, value: impl Into<T>) -> Condition {
+        self.compare("=", &value.into())
+    }
+
+    pub fn ne(&self, value: impl Into<T>) -> Condition {
+        self.compare("!=", &value.into())
+    }
+
+    pub fn lt(&self, value: impl Into<T>) -> Condition {
+        self.compare("<", &value.into())
+    }
+
+    pub fn le(&self, value: impl Into<T>) -> Condition {
+        self.compare("<=", &value.into())
+    }
+
+    pub fn gt(&self, value: impl Into<T>) -> Condition {
+        self.compare(">", &value.into())
+    }
+
+    pub fn ge(&self, value: impl Into<T>) -> Condition {
+        self.compare(">=", &value.into())
+    }
+
+    /// The field equals one of `values`
+    pub fn one_of(&self, values: impl IntoIterator<Item = T>) -> Condition {
+        self.compare("INSIDE", &values.into_iter().collect::<Vec<_>>())
+    }
+}
+
+/// The `WHERE` clause of a `SelectBuilder`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition(Vec<Part>);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Sql(String),
+    /// bound as a `$param` once the condition is written
+    Value(Value),
+}
+
+impl Condition {
+    fn wrap(before: &str, inner: Vec<Part>, after: &str) -> Vec<Part> {
+        let mut parts = vec![Part::Sql(before.to_owned())];
+        parts.extend(inner);
+        parts.push(Part::Sql(after.to_owned()));
+        parts
+    }
+
+    pub fn and(self, other: Condition) -> Self {
+        let mut parts = Self::wrap("(", self.0, ") AND ");
+        parts.extend(Self::wrap("(", other.0, ")"));
+        Self(parts)
+    }
+
+    pub fn or(self, other: Condition) -> Self {
+        let mut parts = Self::wrap("(", self.0, ") OR ");
+        parts.extend(Self::wrap("(", other.0, ")"));
+        Self(parts)
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Self {
+        Self(Self::wrap("!(", self.0, ")"))
+    }
+}
+
+/// Builds a `Selector` from the fields of a `SurrealProps` struct, start it with the generated `query()`
+///
+/// ```ignore
+/// let selector = ToDoItemProps::query()
+///     .filter(|f| f.done.eq(false))
+///     .order_by(|f| f.title)
+///     .selector()?;
+/// ```
+pub struct SelectBuilder<F> {
+    table: &'static str,
+    fields: F,
+    condition: Option<Condition>,
+    order: Vec<(&'static str, bool)>,
+    limit: Option<usize>,
+    start: Option<usize>,
+}
+
+impl<F> SelectBuilder<F> {
+    pub fn new(table: &'static str, fields: F) -> Self {
+        Self {
+            table,
+            fields,
+            condition: None,
+            order: vec![],
+            limit: None,
+            start: None,
+        }
+    }
+
+    /// Only select the records matching the condition, several filters all have to match
+    pub fn filter(mut self, f: impl FnOnce(&F) -> Condition) -> Self {
+        let condition = f(&self.fields);
+        self.condition = Some(match self.condition {
+            Some(existing) => existing.and(condition),
+            None => condition,
+        });
+        self
+    }
+
+    pub fn order_by<T>(mut self, f: impl FnOnce(&F) -> Field<T>) -> Self {
+        self.order.push((f(&self.fields).name, true));
+        self
+    }
+
+    pub fn order_by_desc<T>(mut self, f: impl FnOnce(&F) -> Field<T>) -> Self {
+        self.order.push((f(&self.fields).name, false));
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn start(mut self, start: usize) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    /// The statement with a `$_p<n>` for every compared value and the values to bind to them
+    fn render(&self) -> (String, Vec<(String, Value)>) {
+        let mut sql = format!("SELECT * FROM {}", self.table);
+        let mut values = Vec::new();
+        if let Some(condition) = &self.condition {
+            sql.push_str(" WHERE ");
+            for part in &condition.0 {
+                match part {
+                    Part::Sql(raw) => sql.push_str(raw),
+                    Part::Value(value) => {
+                        let name = format!("_p{}", values.len());
+                        sql.push('$');
+                        sql.push_str(&name);
+                        values.push((name, value.clone()));
+                    }
+                }
+            }
+        }
+        if !self.order.is_empty() {
+            let order = self
+                .order
+                .iter()
+                .map(|(field, ascending)| format!("{} {}", field, if *ascending { "ASC" } else { "DESC" }))
+                .collect::<Vec<_>>();
+            sql.push_str(&format!(" ORDER BY {}", order.join(", ")));
+        }
+        if let Some(limit) = self.limit {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+        if let Some(start) = self.start {
+            sql.push_str(&format!(" START {}", start));
+        }
+        (sql, values)
+    }
+
+    /// The `Selector` with the compared values bound as params, fails if the table name is no valid SurrealQL
+    pub fn selector(&self) -> Result<Selector> {
+        let (sql, values) = self.render();
+        let query = parse(&sql).map_err(|error| SurrealError::Database(surrealdb::Error::Db(error)))?;
+        let json = values
+            .iter()
+            .map(|(name, value)| (name.clone(), serde_json::Value::String(value.to_string())))
+            .collect::<serde_json::Map<_, _>>();
+        let bindings = Bindings::from_fn(json.into(), move |mut query| {
+            for (name, value) in &values {
+                query = query.bind((name.clone(), value.clone()));
+            }
+            query
+        });
+        Ok(Selector {
+            bindings,
+            ..query.into_prop_value()
+        })
+    }
+}
+
+/// The statement with `$_p<n>` placeholders instead of the compared values
+impl<F> Display for SelectBuilder<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.render().0)
+    }
+}
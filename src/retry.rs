@@ -0,0 +1,25 @@
+use std::{future::IntoFuture, time::Duration};
+
+use crate::{config::RetryPolicy, error::Result, timeout::timeout};
+
+/// Send the request built by `make` and send it again as long as `policy` allows it
+pub(crate) async fn send<T, F>(make: impl Fn() -> F, duration: Option<Duration>, policy: &RetryPolicy) -> Result<T>
+where
+    F: IntoFuture<Output = surrealdb::Result<T>>,
+{
+    let mut attempt = 1;
+    loop {
+        let result = match timeout(duration, make()).await {
+            Ok(Ok(data)) => Ok(data),
+            Ok(Err(error)) => Err(error.into()),
+            Err(error) => Err(error.into()),
+        };
+        match result {
+            Err(error) if policy.should_retry(attempt, &error) => {
+                yew::platform::time::sleep(policy.delay(attempt)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
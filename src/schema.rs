@@ -0,0 +1,62 @@
+//! SurrealQL schemas generated from structs with `#[derive(SurrealSchema)]`
+/// A table and the types of its fields, derive it together with `SurrealProps`:
+///
+/// ```ignore
+/// #[derive(SurrealProps, SurrealSchema, Properties, PartialEq, Clone)]
+/// #[surreal(table = "item")]
+/// struct ToDoItemProps { ... }
+/// ```
+///
+/// `bool` is stored as `bool`, `AttrValue` and `String` as `string`, `Vec<T>` as `array` and `StaticChild<T>` as
+/// `record(...)` of the table of `T`. `Option`s, `ID`s and fields of other types are defined without a type
+pub trait SurrealSchema {
+    /// The table the records are stored in
+    const TABLE: &'static str;
+
+    /// `DEFINE TABLE` followed by a `DEFINE FIELD` for every field that is stored in the database
+    fn statements() -> Vec<String>;
+
+    /// All statements as a single query
+    fn schema() -> String {
+        Self::statements().join(";\n") + ";"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use surrealdb::sql::parse;
+
+    use crate::{
+        props::{children::StaticChild, id::ID},
+        SurrealSchema,
+    };
+
+    #[derive(SurrealSchema, Clone, PartialEq)]
+    #[surreal(table = "person")]
+    #[allow(dead_code)]
+    struct Person {
+        id: Option<ID>,
+        name: String,
+    }
+
+    #[derive(SurrealSchema, Clone, PartialEq)]
+    #[surreal(table = "article")]
+    #[allow(dead_code)]
+    struct Article {
+        id: Option<ID>,
+        title: String,
+        text: Option<String>,
+        tags: Vec<String>,
+        editor: ID,
+        author: StaticChild<Person>,
+    }
+
+    #[test]
+    fn schema_parses() {
+        let schema = Article::schema();
+        assert!(parse(&schema).is_ok(), "{}", schema);
+        assert!(schema.contains("DEFINE FIELD tags ON article TYPE array;"));
+        assert!(schema.contains("DEFINE FIELD author ON article TYPE record(person)"));
+        assert!(schema.contains("DEFINE FIELD text ON article;"));
+    }
+}
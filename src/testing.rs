@@ -0,0 +1,521 @@
+//! Helpers for testing components without a running database
+//!
+//! Only compiled with the `testing` feature. `<MockSurrealContext/>` connects to an in-memory database and answers queries
+//! from fixtures or from traffic recorded with a `TrafficRecorder`
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+    time::Duration,
+};
+
+use futures::lock::Mutex;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use surrealdb::{
+    engine::any::Any,
+    opt::IntoQuery,
+    Response, Surreal,
+};
+use yew::{function_component, html, use_effect_with_deps, Children, Html, Properties};
+
+use crate::{
+    components::SurrealContext,
+    config::ConnectionConfig,
+    error::{Result, SurrealError},
+    hooks::{use_surreal_token_with_client, SignInError, SurrealToken},
+    intercept,
+    logging::{self, handle_error},
+};
+
+pub use crate::traffic::Recorded;
+
+thread_local! {
+    /// In-memory clients whose mock was dropped and whether they are connected yet, the next mock reuses them
+    static CLIENTS: RefCell<Vec<(&'static Surreal<Any>, bool)>> = RefCell::new(Vec::new());
+    static DATABASES: Cell<u64> = Cell::new(0);
+    /// Hands recorded results back to the caller, it holds no data so all recorders of a thread share it
+    static ECHO: (&'static Surreal<Any>, Rc<Mutex<bool>>) = (Box::leak(Box::new(Surreal::init())), Rc::new(Mutex::new(false)));
+}
+
+/// A request together with the result of each of its statements or the error it failed with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Exchange {
+    pub request: Recorded,
+    pub response: std::result::Result<Vec<Vec<Value>>, String>,
+}
+
+/// Everything a `TrafficRecorder` saw, in the order the responses arrived
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Traffic(pub Vec<Exchange>);
+
+impl Traffic {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_json())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        Ok(Self::from_json(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+struct Inner {
+    client: &'static Surreal<Any>,
+    connected: Cell<bool>,
+    /// unique per mock so a reused client does not see the records of the previous one
+    database: String,
+    fixtures: RefCell<HashMap<String, Vec<Value>>>,
+    failures: RefCell<HashMap<String, String>>,
+    recorded: RefCell<Vec<Recorded>>,
+    replay: RefCell<Option<Vec<Exchange>>>,
+    latency: Cell<Option<Duration>>,
+    offline: Cell<bool>,
+}
+
+/// The scripted database behind a `<MockSurrealContext/>`
+///
+/// Every mock has its own in-memory database, records created during a test are visible to later selects.
+/// Clients are only leaked once per mock alive at the same time, a dropped mock hands its client to the next one
+#[derive(Clone)]
+pub struct MockDatabase(Rc<Inner>);
+
+impl PartialEq for MockDatabase {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Default for MockDatabase {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        CLIENTS.with(|clients| clients.borrow_mut().push((self.client, self.connected.get())));
+    }
+}
+
+impl MockDatabase {
+    pub fn new() -> Self {
+        // the token needs a `'static` client, so it is leaked unless a dropped mock left one behind
+        let (client, connected) = CLIENTS
+            .with(|clients| clients.borrow_mut().pop())
+            .unwrap_or_else(|| (Box::leak(Box::new(Surreal::init())), false));
+        let database = DATABASES.with(|databases| {
+            databases.set(databases.get() + 1);
+            format!("test{}", databases.get())
+        });
+        Self(Rc::new(Inner {
+            client,
+            connected: Cell::new(connected),
+            database,
+            fixtures: RefCell::new(HashMap::new()),
+            failures: RefCell::new(HashMap::new()),
+            recorded: RefCell::new(Vec::new()),
+            replay: RefCell::new(None),
+            latency: Cell::new(None),
+            offline: Cell::new(false),
+        }))
+    }
+
+    /// Answer `selector` with `rows` instead of asking the database, a single row may be passed without a list
+    ///
+    /// Also answers `select` with the first row, e.g. `"SELECT * FROM item:1"` for `token.select(thing)`
+    pub fn fixture(self, selector: impl IntoQuery, rows: impl Serialize) -> Self {
+        let rows = match serde_json::to_value(rows).unwrap_or_default() {
+            Value::Array(rows) => rows,
+            row => vec![row],
+        };
+        self.0.fixtures.borrow_mut().insert(statement(selector), rows);
+        self
+    }
+
+    /// Fail every request whose statement starts with `prefix` (e.g. `"DELETE"`) with a database error
+    pub fn fail(self, prefix: impl Into<String>, message: impl Into<String>) -> Self {
+        self.0.failures.borrow_mut().insert(prefix.into(), message.into());
+        self
+    }
+
+    /// Answer queries with the responses of a recording instead of asking the database
+    ///
+    /// Each query gets the first unused response that was recorded for the same statement and bindings,
+    /// queries that were never recorded fail
+    pub fn replay(self, traffic: Traffic) -> Self {
+        *self.0.replay.borrow_mut() = Some(traffic.0);
+        self
+    }
+
+    /// Wait `latency` before answering each request
+    pub fn latency(self, latency: Duration) -> Self {
+        self.0.latency.set(Some(latency));
+        self
+    }
+
+    /// While offline every request fails as if the database could not be reached
+    pub fn set_offline(&self, offline: bool) {
+        self.0.offline.set(offline);
+    }
+
+    /// All requests sent so far, oldest first
+    pub fn recorded(&self) -> Vec<Recorded> {
+        self.0.recorded.borrow().clone()
+    }
+
+    pub fn clear_recorded(&self) {
+        self.0.recorded.borrow_mut().clear();
+    }
+
+    pub(crate) fn client(&self) -> &'static Surreal<Any> {
+        self.0.client
+    }
+
+    /// Connect `token` to the in-memory database of this mock
+    pub(crate) async fn connect(&self, token: &SurrealToken) -> std::result::Result<(), SignInError> {
+        if !self.0.connected.get() {
+            token.connect("mem://".to_owned()).await?;
+            self.0.connected.set(true);
+        }
+        token.use_embedded("test".to_owned(), self.0.database.clone()).await
+    }
+
+    pub(crate) fn rows(&self, query: impl IntoQuery) -> Option<Vec<Value>> {
+        self.0.fixtures.borrow().get(&statement(query)).cloned()
+    }
+
+    /// The first row of the fixture for `statement`, for requests that are answered with a single value like a `select`
+    pub(crate) fn fixture_value<T: DeserializeOwned>(&self, statement: &str) -> Option<Result<T>> {
+        let row = self.rows(statement)?.into_iter().next().unwrap_or_default();
+        Some(serde_json::from_value(row).map_err(|error| thrown(error.to_string())))
+    }
+
+    /// Record the request, then wait and fail as scripted
+    pub(crate) async fn check(&self, request: &Recorded) -> Result<()> {
+        let mock = &self.0;
+        mock.recorded.borrow_mut().push(request.clone());
+        if let Some(latency) = mock.latency.get() {
+            yew::platform::time::sleep(latency).await;
+        }
+        if mock.offline.get() {
+            return Err(surrealdb::Error::Api(surrealdb::error::Api::ConnectionUninitialised).into());
+        }
+        let failure = mock
+            .failures
+            .borrow()
+            .iter()
+            .find(|(prefix, _)| request.statement.starts_with(prefix.as_str()))
+            .map(|(_, message)| message.clone());
+        match failure {
+            Some(message) => Err(thrown(message)),
+            None => Ok(()),
+        }
+    }
+
+    /// Take the recorded response to `request` from the replay, `None` if nothing is replayed
+    fn take_replayed(&self, request: &Recorded) -> Option<std::result::Result<Vec<Vec<Value>>, String>> {
+        let mut replay = self.0.replay.borrow_mut();
+        let replay = replay.as_mut()?;
+        Some(match replay.iter().position(|exchange| &exchange.request == request) {
+            Some(index) => replay.remove(index).response,
+            None => Err(format!("No response was recorded for {}", request.statement)),
+        })
+    }
+
+    /// Answer `request` from the replay, `None` if nothing is replayed
+    pub(crate) async fn replayed(&self, request: &Recorded) -> Result<Option<Response>> {
+        match self.take_replayed(request) {
+            None => Ok(None),
+            Some(Ok(results)) => Ok(Some(respond_with(self.client(), results).await?)),
+            Some(Err(message)) => Err(thrown(message)),
+        }
+    }
+
+    /// Like `replayed` for requests that are answered with a single value instead of a `Response`, e.g. a `select`
+    pub(crate) fn replayed_value<T: DeserializeOwned>(&self, request: &Recorded) -> Result<Option<T>> {
+        match self.take_replayed(request) {
+            None => Ok(None),
+            Some(Ok(results)) => {
+                let value = results
+                    .into_iter()
+                    .next()
+                    .and_then(|rows| rows.into_iter().next())
+                    .unwrap_or_default();
+                serde_json::from_value(value)
+                    .map(Some)
+                    .map_err(|error| thrown(error.to_string()))
+            }
+            Some(Err(message)) => Err(thrown(message)),
+        }
+    }
+}
+
+fn thrown(message: String) -> SurrealError {
+    surrealdb::Error::Db(surrealdb::error::Db::Thrown(message)).into()
+}
+
+/// Records every query sent through a `SurrealToken` together with its response
+///
+/// Attach it with `SurrealToken::with_recorder`, the responses are printed as well if the `log_traffic` feature is enabled.
+/// The recording can be replayed with `MockDatabase::replay`
+#[derive(Clone, Default)]
+pub struct TrafficRecorder(Rc<RefCell<Vec<Exchange>>>);
+
+impl PartialEq for TrafficRecorder {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl TrafficRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Everything recorded so far
+    pub fn traffic(&self) -> Traffic {
+        Traffic(self.0.borrow().clone())
+    }
+
+    pub fn clear(&self) {
+        self.0.borrow_mut().clear();
+    }
+
+    fn push(&self, exchange: Exchange) {
+        logging::print_traffic(logging::Operation::Receive, &exchange);
+        self.0.borrow_mut().push(exchange);
+    }
+
+    /// Record the result of a request that is not answered with a `Response`, e.g. a `select` or a `create`
+    ///
+    /// The value is stored as the only row of a single statement, `None` as no row at all
+    pub(crate) fn record_value<T: Serialize>(&self, request: Recorded, result: &Result<T>) {
+        let response = match result {
+            Ok(value) => match serde_json::to_value(value).unwrap_or_default() {
+                Value::Null => Ok(vec![Vec::new()]),
+                value => Ok(vec![vec![value]]),
+            },
+            Err(error) => Err(error.to_string()),
+        };
+        self.push(Exchange { request, response });
+    }
+
+    pub(crate) async fn record(&self, request: Recorded, response: Result<Response>, statements: usize) -> Result<Response> {
+        let results = response.and_then(|mut response| {
+            (0..statements)
+                // a result that is not a list can't be replayed, recording it as empty would hide that
+                .map(|index| response.take::<Vec<Value>>(index).map_err(SurrealError::from))
+                .collect::<Result<Vec<_>>>()
+        });
+        self.push(Exchange {
+            request,
+            response: results.as_ref().map_err(ToString::to_string).cloned(),
+        });
+
+        let results = results?;
+        let (echo, connected) = ECHO.with(|(echo, connected)| (*echo, connected.clone()));
+        let mut connected = connected.lock().await;
+        if !*connected {
+            echo.connect("mem://").await?;
+            echo.use_ns("echo").use_db("echo").await?;
+            *connected = true;
+        }
+        Ok(respond_with(echo, results).await?)
+    }
+}
+
+/// Turn results back into a `Response` by selecting them from an in-memory database
+async fn respond_with(client: &Surreal<Any>, results: Vec<Vec<Value>>) -> surrealdb::Result<Response> {
+    let statement = (0..results.len())
+        .map(|index| format!("SELECT * FROM $result{}", index))
+        .collect::<Vec<_>>()
+        .join("; ");
+    let mut query = client.query(statement);
+    for (index, rows) in results.into_iter().enumerate() {
+        query = query.bind((format!("result{}", index), rows));
+    }
+    query.await
+}
+
+/// The normalized text of a query, used to match fixtures regardless of formatting
+fn statement(query: impl IntoQuery) -> String {
+    intercept::parse(query).0
+}
+
+#[derive(Properties, PartialEq)]
+pub struct MockSurrealContextProps {
+    pub mock: MockDatabase,
+    pub fallback: Option<Html>,
+    pub children: Children,
+}
+
+/// A `<SurrealContext/>` backed by a `MockDatabase`, use it in place of the real context in component tests
+#[function_component(MockSurrealContext)]
+pub fn mock_surreal_context(props: &MockSurrealContextProps) -> Html {
+    let mut token = use_surreal_token_with_client(props.mock.client(), ConnectionConfig::default());
+
+    {
+        let token = token.clone();
+        let mock = props.mock.clone();
+        use_effect_with_deps(
+            move |_| {
+                yew::platform::spawn_local(async move {
+                    if let Err(error) = mock.connect(&token).await {
+                        handle_error(error);
+                    }
+                });
+            },
+            (),
+        );
+    }
+
+    token.mock = Some(props.mock.clone());
+    html! {
+        <SurrealContext {token} fallback={props.fallback.clone()}>
+            { for props.children.iter() }
+        </SurrealContext>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use serde_json::json;
+
+    use super::*;
+
+    fn request(statement: &str) -> Recorded {
+        Recorded {
+            statement: statement.to_owned(),
+            bindings: Vec::new(),
+        }
+    }
+
+    async fn connected(mock: MockDatabase) -> MockDatabase {
+        if !mock.0.connected.get() {
+            mock.client().connect("mem://").await.unwrap();
+            mock.0.connected.set(true);
+        }
+        mock.client().use_ns("test").use_db(mock.0.database.clone()).await.unwrap();
+        mock
+    }
+
+    #[test]
+    fn fixtures_match_regardless_of_formatting() {
+        let mock = MockDatabase::new().fixture("SELECT * FROM item", json!({"id": "item:1"}));
+        assert_eq!(mock.rows("SELECT  *\n  FROM item"), Some(vec![json!({"id": "item:1"})]));
+        assert_eq!(mock.rows("SELECT * FROM other"), None);
+    }
+
+    #[test]
+    fn fixtures_answer_selects() {
+        let mock = MockDatabase::new().fixture("SELECT * FROM item:1", json!({"title": "Milk"}));
+        let milk = mock.fixture_value::<Value>("SELECT * FROM item:1").unwrap();
+        assert_eq!(milk.unwrap(), json!({"title": "Milk"}));
+        assert!(mock.fixture_value::<Value>("SELECT * FROM item:2").is_none());
+    }
+
+    #[test]
+    fn scripted_failures() {
+        let mock = MockDatabase::new().fail("DELETE", "permission denied");
+        block_on(async {
+            assert!(mock.check(&request("DELETE item:1")).await.is_err());
+            assert!(mock.check(&request("SELECT * FROM item")).await.is_ok());
+            mock.set_offline(true);
+            let offline = mock.check(&request("SELECT * FROM item")).await;
+            assert!(offline.unwrap_err().is_offline());
+        });
+        assert_eq!(mock.recorded().len(), 3);
+        mock.clear_recorded();
+        assert!(mock.recorded().is_empty());
+    }
+
+    #[test]
+    fn dropped_mocks_hand_their_client_on() {
+        let dropped = MockDatabase::new();
+        let (client, database) = (dropped.client(), dropped.0.database.clone());
+        drop(dropped);
+
+        let mock = MockDatabase::new();
+        assert!(std::ptr::eq(mock.client(), client));
+        assert_ne!(mock.0.database, database);
+        assert!(!std::ptr::eq(MockDatabase::new().client(), client));
+    }
+
+    #[test]
+    fn traffic_survives_json() {
+        let traffic = Traffic(vec![Exchange {
+            request: Recorded {
+                statement: "UPDATE $thing MERGE $data RETURN NONE".to_owned(),
+                bindings: vec![json!({"thing": "item:1", "data": {"done": true}})],
+            },
+            response: Err("permission denied".to_owned()),
+        }]);
+        assert_eq!(Traffic::from_json(&traffic.to_json()).unwrap(), traffic);
+    }
+
+    #[tokio::test]
+    async fn replays_responses_in_recorded_order() {
+        let exchange = |n| Exchange {
+            request: request("SELECT * FROM item"),
+            response: Ok(vec![vec![json!({"n": n})]]),
+        };
+        let mock = connected(MockDatabase::new().replay(Traffic(vec![exchange(1), exchange(2)]))).await;
+
+        for n in 1..=2 {
+            let mut response = mock.replayed(&request("SELECT * FROM item")).await.unwrap().unwrap();
+            assert_eq!(response.take::<Vec<Value>>(0).unwrap(), vec![json!({"n": n})]);
+        }
+        // every response is only used once
+        assert!(mock.replayed(&request("SELECT * FROM item")).await.is_err());
+        assert!(mock.replayed(&request("SELECT * FROM other")).await.is_err());
+        assert!(MockDatabase::new().replayed(&request("SELECT * FROM item")).await.unwrap().is_none());
+    }
+
+    #[test]
+    fn values_are_recorded_and_replayed() {
+        let recorder = TrafficRecorder::new();
+        recorder.record_value(request("SELECT * FROM item:1"), &Ok(json!({"title": "Milk"})));
+        recorder.record_value(request("SELECT * FROM item:2"), &Ok(None::<Value>));
+        recorder.record_value::<Value>(request("CREATE item"), &Err(thrown("permission denied".to_owned())));
+
+        let mock = MockDatabase::new().replay(recorder.traffic());
+        let milk = mock.replayed_value::<Value>(&request("SELECT * FROM item:1"));
+        assert_eq!(milk.unwrap(), Some(json!({"title": "Milk"})));
+        let missing = mock.replayed_value::<Option<Value>>(&request("SELECT * FROM item:2"));
+        assert_eq!(missing.unwrap(), Some(None));
+        assert!(mock.replayed_value::<Value>(&request("CREATE item")).is_err());
+    }
+
+    #[tokio::test]
+    async fn recorder_passes_the_response_on() {
+        let mock = connected(MockDatabase::new()).await;
+        let recorder = TrafficRecorder::new();
+
+        let response = respond_with(mock.client(), vec![vec![json!({"title": "Milk"})]]).await;
+        let mut response = recorder
+            .record(request("SELECT * FROM item"), response.map_err(Into::into), 1)
+            .await
+            .unwrap();
+        assert_eq!(response.take::<Vec<Value>>(0).unwrap(), vec![json!({"title": "Milk"})]);
+
+        let failed = recorder
+            .record(request("DELETE item:1"), Err(thrown("permission denied".to_owned())), 1)
+            .await;
+        assert!(failed.is_err());
+
+        let traffic = recorder.traffic().0;
+        assert_eq!(traffic[0].response, Ok(vec![vec![json!({"title": "Milk"})]]));
+        assert!(traffic[1].response.is_err());
+    }
+}
@@ -0,0 +1,30 @@
+use std::{error::Error, fmt::Display, future::IntoFuture, time::Duration};
+
+use futures::future::{select, Either};
+
+/// The database did not respond in time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimedOut(pub Duration);
+
+impl Display for TimedOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The database did not respond within {:?}", self.0)
+    }
+}
+
+impl Error for TimedOut {}
+
+/// Wait for `future` but give up after `duration`, waits forever if `duration` is `None`
+pub(crate) async fn timeout<F: IntoFuture>(duration: Option<Duration>, future: F) -> Result<F::Output, TimedOut> {
+    let future = future.into_future();
+    match duration {
+        None => Ok(future.await),
+        Some(duration) => {
+            let sleep = Box::pin(yew::platform::time::sleep(duration));
+            match select(Box::pin(future), sleep).await {
+                Either::Left((output, _)) => Ok(output),
+                Either::Right(_) => Err(TimedOut(duration)),
+            }
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! Structured logging of every request sent through a `SurrealToken`
+//!
+//! Pass a `TrafficLog` to `<SurrealContext/>` and add sinks to it, they can be added and removed while the app is running
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    rc::Rc,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use yew::Callback;
+
+use crate::logging;
+
+/// What kind of request a `TrafficEvent` belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum RequestKind {
+    Query,
+    Select,
+    Create,
+    Update,
+    Delete,
+}
+
+/// A request as it was sent through a `SurrealToken`, recorded by the mocks and recorders of `syewreal::testing`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recorded {
+    /// The statements of the request, e.g. `UPDATE $thing MERGE $data RETURN NONE`
+    pub statement: String,
+    /// Everything that was bound to the request, in order
+    pub bindings: Vec<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum TrafficPhase {
+    /// The request is about to be sent
+    Sent { bindings: Vec<Value> },
+    /// The database answered
+    Received { elapsed: Duration },
+    /// The request failed or timed out
+    Failed { elapsed: Duration, error: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TrafficEvent {
+    /// Shared by all events of the same request
+    pub id: u64,
+    pub kind: RequestKind,
+    pub statement: String,
+    pub phase: TrafficPhase,
+}
+
+/// A sink for `TrafficEvent`s
+pub trait TrafficLogger {
+    fn log(&self, event: &TrafficEvent);
+}
+
+impl<T: TrafficLogger> TrafficLogger for Rc<T> {
+    fn log(&self, event: &TrafficEvent) {
+        (**self).log(event)
+    }
+}
+
+impl TrafficLogger for Callback<TrafficEvent> {
+    fn log(&self, event: &TrafficEvent) {
+        self.emit(event.clone())
+    }
+}
+
+/// Prints every event to the JS console, or to stdout on native targets
+pub struct ConsoleLogger;
+
+impl TrafficLogger for ConsoleLogger {
+    fn log(&self, event: &TrafficEvent) {
+        let phase = match &event.phase {
+            TrafficPhase::Sent { bindings } => format!("sent {}", Value::from(bindings.clone())),
+            TrafficPhase::Received { elapsed } => format!("received after {:?}", elapsed),
+            TrafficPhase::Failed { elapsed, error } => format!("failed after {:?}: {}", elapsed, error),
+        };
+        logging::print_message(&format!("#{} {:?} {} {}", event.id, event.kind, event.statement, phase));
+    }
+}
+
+/// Keeps the last `capacity` events in memory, wrap it in an `Rc` to read them while it is logging
+pub struct RingBuffer {
+    capacity: usize,
+    events: RefCell<VecDeque<TrafficEvent>>,
+}
+
+impl RingBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            events: RefCell::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// The buffered events, oldest first
+    pub fn events(&self) -> Vec<TrafficEvent> {
+        self.events.borrow().iter().cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        self.events.borrow_mut().clear();
+    }
+}
+
+impl TrafficLogger for RingBuffer {
+    fn log(&self, event: &TrafficEvent) {
+        let mut events = self.events.borrow_mut();
+        if events.len() == self.capacity {
+            events.pop_front();
+        }
+        if self.capacity > 0 {
+            events.push_back(event.clone());
+        }
+    }
+}
+
+struct Inner {
+    sinks: RefCell<Vec<Rc<dyn TrafficLogger>>>,
+    enabled: Cell<bool>,
+}
+
+/// The sinks the traffic of a `<SurrealContext/>` is logged to
+#[derive(Clone)]
+pub struct TrafficLog(Rc<Inner>);
+
+impl PartialEq for TrafficLog {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Default for TrafficLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TrafficLog {
+    pub fn new() -> Self {
+        Self(Rc::new(Inner {
+            sinks: RefCell::new(Vec::new()),
+            enabled: Cell::new(true),
+        }))
+    }
+
+    /// Log to `sink` as well
+    pub fn with(self, sink: impl TrafficLogger + 'static) -> Self {
+        self.add(sink);
+        self
+    }
+
+    pub fn add(&self, sink: impl TrafficLogger + 'static) {
+        self.0.sinks.borrow_mut().push(Rc::new(sink));
+    }
+
+    #[cfg(feature = "devtools")]
+    pub(crate) fn add_shared(&self, sink: Rc<dyn TrafficLogger>) {
+        self.0.sinks.borrow_mut().push(sink);
+    }
+
+    #[cfg(feature = "devtools")]
+    pub(crate) fn remove_shared(&self, sink: &Rc<dyn TrafficLogger>) {
+        self.0.sinks.borrow_mut().retain(|other| !Rc::ptr_eq(other, sink));
+    }
+
+    /// Remove all sinks
+    pub fn clear(&self) {
+        self.0.sinks.borrow_mut().clear();
+    }
+
+    /// Stop or resume logging without touching the sinks
+    pub fn set_enabled(&self, enabled: bool) {
+        self.0.enabled.set(enabled);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.0.enabled.get()
+    }
+
+    pub(crate) fn log(&self, event: &TrafficEvent) {
+        if !self.is_enabled() {
+            return;
+        }
+        // a sink may change the log while it is called
+        let sinks = self.0.sinks.borrow().clone();
+        for sink in sinks {
+            sink.log(event);
+        }
+    }
+}
+
+/// A new correlation id
+pub(crate) fn next_id() -> u64 {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Measures how long a request took
+#[derive(Clone, Copy)]
+pub(crate) struct Stopwatch(f64);
+
+impl Stopwatch {
+    pub(crate) fn start() -> Self {
+        Self(now())
+    }
+
+    pub(crate) fn elapsed(&self) -> Duration {
+        Duration::from_secs_f64((now() - self.0).max(0.0) / 1000.0)
+    }
+}
+
+/// Milliseconds since some fixed point in time, `std::time::Instant` is not available in the browser
+#[cfg(target_arch = "wasm32")]
+fn now() -> f64 {
+    web_sys::window()
+        .and_then(|window| window.performance())
+        .map(|performance| performance.now())
+        .unwrap_or_default()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn now() -> f64 {
+    lazy_static::lazy_static! {
+        static ref START: std::time::Instant = std::time::Instant::now();
+    }
+    START.elapsed().as_secs_f64() * 1000.0
+}
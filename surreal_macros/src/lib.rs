@@ -1,56 +1,266 @@
 use proc_macro::TokenStream;
-use quote::quote;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
 use surrealdb::sql::{parse, Statement};
-use syn::{parse_macro_input, Data, DeriveInput, Field, Ident, __private::Span};
+use syn::{ext::IdentExt, parse_macro_input, Data, DeriveInput, Error, Field, Fields, Ident};
 
-/// Parse the sql during compile time to safely unwrap it during runtime
+mod schema;
+mod sql;
+
+use schema::{derive_schema, Schema, SurrealAttr};
+use sql::Sql;
+
+/// Parse the sql during compile time to safely unwrap it during runtime, returns a `surrealdb::sql::Query`
+///
+/// `$params` are left to the caller, bind them with `.bind` or use `bound_sqlx!` to take them from variables.
+///
+/// SurrealQL that is no valid Rust (`<-`, `'single quoted'` strings, `@@`) can be passed as a string literal instead.
+/// `{name}` in the literal is replaced with a table or field name given after it, e.g.
+/// `sqlx!("SELECT * FROM {table} WHERE {field} @@ $search", table = item, field = "title")`
 #[proc_macro]
 pub fn sqlx(item: TokenStream) -> TokenStream {
-    match parse(item.to_string().as_str()) {
-        Err(err) => panic!("Syntax error: {}", err.to_string().as_str()),
-        Ok(_) => {
-            let item: quote::__private::TokenStream = item.into();
+    let sql = match Sql::from_input("", item) {
+        Ok(sql) => sql,
+        Err(error) => return error.into_compile_error().into(),
+    };
+    if let Err(error) = sql.parse() {
+        return error.into_compile_error().into();
+    }
+    let raw = &sql.raw;
 
-            quote!{
-                {
-                    let query: surrealdb::sql::Query = surrealdb::sql::parse(stringify!(#item)).expect("Checked during compile time");
-                    query
-                }
-            }.into()
+    quote!{
+        {
+            let query: ::syewreal::surrealdb::sql::Query = ::syewreal::surrealdb::sql::parse(#raw).expect("Checked during compile time");
+            query
         }
+    }.into()
+}
+
+/// Like `sqlx!`, but every `$param` is taken from a variable of the same name in the calling scope (like the
+/// arguments of `format!`). Returns a `syewreal::query::BoundQuery`, its bindings are a struct with one field per param
+#[proc_macro]
+pub fn bound_sqlx(item: TokenStream) -> TokenStream {
+    let sql = match Sql::from_input("", item) {
+        Ok(sql) => sql,
+        Err(error) => return error.into_compile_error().into(),
+    };
+    if let Err(error) = sql.parse() {
+        return error.into_compile_error().into();
     }
+    let raw = &sql.raw;
+    let params = &sql.params;
+    let types = (0..params.len()).map(|index| format_ident!("T{}", index)).collect::<Vec<_>>();
+
+    quote!{
+        {
+            #[derive(::syewreal::serde::Serialize, Clone, Debug)]
+            #[serde(crate = "::syewreal::serde")]
+            struct Bindings<#(#types),*> {
+                #(#params: #types,)*
+            }
+
+            let query: ::syewreal::surrealdb::sql::Query = ::syewreal::surrealdb::sql::parse(#raw).expect("Checked during compile time");
+            ::syewreal::query::BoundQuery::new(query, Bindings {
+                #(#params: ::std::clone::Clone::clone(&#params),)*
+            })
+        }
+    }.into()
 }
 
 #[proc_macro]
 #[allow(non_snake_case)]
 pub fn SELECT(item: TokenStream) -> TokenStream {
-    let raw = "SELECT ".to_owned() + &item.to_string();
-    match parse(raw.as_str()) {
-        Err(err) => panic!("Syntax error: {}", err.to_string().as_str()),
-        Ok(stmt) => match stmt.to_vec().first() {
-            Some(Statement::Select(_)) => {
-                quote!{
-                    match surrealdb::sql::parse(#raw)
-                        .expect("Checked during compile time").first() {
-                            Some(Statement::Select(x)) => x.clone(),
-                            _ => panic!()
-                        }
-                }.into()
-            },
-            Some(_) => panic!("{} is not a SELECT statement", item.to_string()),
-            None => panic!("Empty statement")
+    statement("SELECT", "Select", item)
+}
+
+/// `UPDATE` checked during compile time, returns a `surrealdb::sql::statements::UpdateStatement`
+#[proc_macro]
+#[allow(non_snake_case)]
+pub fn UPDATE(item: TokenStream) -> TokenStream {
+    statement("UPDATE", "Update", item)
+}
+
+/// `CREATE` checked during compile time, returns a `surrealdb::sql::statements::CreateStatement`
+#[proc_macro]
+#[allow(non_snake_case)]
+pub fn CREATE(item: TokenStream) -> TokenStream {
+    statement("CREATE", "Create", item)
+}
+
+/// `DELETE` checked during compile time, returns a `surrealdb::sql::statements::DeleteStatement`
+#[proc_macro]
+#[allow(non_snake_case)]
+pub fn DELETE(item: TokenStream) -> TokenStream {
+    statement("DELETE", "Delete", item)
+}
+
+/// `RELATE` checked during compile time, returns a `surrealdb::sql::statements::RelateStatement`
+#[proc_macro]
+#[allow(non_snake_case)]
+pub fn RELATE(item: TokenStream) -> TokenStream {
+    statement("RELATE", "Relate", item)
+}
+
+/// Parse `keyword` followed by the tokens during compile time and unwrap the `Statement::<variant>` during runtime
+fn statement(keyword: &str, variant: &str, item: TokenStream) -> TokenStream {
+    // the errors below point at the whole statement
+    let input = TokenStream2::from(item.clone());
+    let sql = match Sql::from_input(&(keyword.to_owned() + " "), item) {
+        Ok(sql) => sql,
+        Err(error) => return error.into_compile_error().into(),
+    };
+    let raw = &sql.raw;
+    let variant = Ident::new(variant, Span::call_site());
+
+    let statements = match sql.parse() {
+        Ok(query) => query.to_vec(),
+        Err(error) => return error.into_compile_error().into(),
+    };
+    let error = match statements.as_slice() {
+        [statement] if statement_name(statement) == Some(keyword) => {
+            return quote!{
+                match ::syewreal::surrealdb::sql::parse(#raw)
+                    .expect("Checked during compile time").first() {
+                        Some(::syewreal::surrealdb::sql::Statement::#variant(x)) => x.clone(),
+                        _ => unreachable!("Checked during compile time")
+                    }
+            }.into();
         }
+        [_] => format!(
+            "`{}` is not a {} statement\nhelp: use `sqlx!` for other statements",
+            raw.trim(),
+            keyword
+        ),
+        [] => format!("Empty statement\nhelp: write the {} statement without the {} keyword", keyword, keyword),
+        _ => format!(
+            "Expected a single {} statement\nhelp: use `sqlx!` to send several statements at once",
+            keyword
+        ),
+    };
+    Error::new_spanned(input, error).into_compile_error().into()
+}
+
+fn statement_name(statement: &Statement) -> Option<&'static str> {
+    match statement {
+        Statement::Select(_) => Some("SELECT"),
+        Statement::Update(_) => Some("UPDATE"),
+        Statement::Create(_) => Some("CREATE"),
+        Statement::Delete(_) => Some("DELETE"),
+        Statement::Relate(_) => Some("RELATE"),
+        _ => None,
     }
 }
 
-#[proc_macro_derive(SurrealProps, attributes(local, fallback, id))]
+/// Embed every `<version>_<name>.surql` file of a directory (relative to the crate root) as `syewreal::migrations::Migrations`
+///
+/// The files are parsed at compile time. Changes to an embedded file trigger a rebuild, new files are only picked up
+/// once the calling crate is rebuilt for another reason
+#[proc_macro]
+pub fn embed_migrations(item: TokenStream) -> TokenStream {
+    let dir = parse_macro_input!(item as syn::LitStr);
+    migrations(&dir).unwrap_or_else(Error::into_compile_error).into()
+}
+
+fn migrations(dir: &syn::LitStr) -> syn::Result<TokenStream2> {
+    let path = std::path::PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default()).join(dir.value());
+    let entries = std::fs::read_dir(&path).map_err(|error| {
+        Error::new_spanned(
+            dir,
+            format!("Could not read the migrations in {}: {}\nhelp: the path is relative to the crate root", path.display(), error),
+        )
+    })?;
+
+    let mut migrations = Vec::new();
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        if path.extension().map_or(true, |extension| extension != "surql") {
+            continue;
+        }
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        let (version, name) = stem
+            .split_once('_')
+            .and_then(|(version, name)| Some((version.parse::<u64>().ok()?, name.to_owned())))
+            .ok_or_else(|| {
+                Error::new_spanned(
+                    dir,
+                    format!(
+                        "The migration {} is not named <version>_<name>.surql\nhelp: e.g. 0001_create_items.surql",
+                        path.display()
+                    ),
+                )
+            })?;
+        let raw = std::fs::read_to_string(&path).map_err(|error| {
+            Error::new_spanned(dir, format!("Could not read the migration {}: {}", path.display(), error))
+        })?;
+        let statements = parse(&raw)
+            .map_err(|error| Error::new_spanned(dir, format!("Syntax error in {}: {}", path.display(), error)))?;
+        // a transaction inside the one the migration runs in would commit or cancel it halfway through
+        if let Some(statement) = statements
+            .iter()
+            .find(|statement| matches!(statement, Statement::Begin(_) | Statement::Commit(_) | Statement::Cancel(_)))
+        {
+            return Err(Error::new_spanned(
+                dir,
+                format!(
+                    "The migration {} contains `{}`\nhelp: every migration already runs in its own transaction, remove the statement",
+                    path.display(),
+                    statement
+                ),
+            ));
+        }
+        if let Some((_, other, _)) = migrations.iter().find(|(other, _, _)| *other == version) {
+            return Err(Error::new_spanned(
+                dir,
+                format!(
+                    "The migrations {} and {} share the version {}\nhelp: every migration needs its own version",
+                    other, name, version
+                ),
+            ));
+        }
+        migrations.push((version, name, path.to_string_lossy().into_owned()));
+    }
+    migrations.sort_by_key(|(version, _, _)| *version);
+
+    let migrations = migrations.into_iter().map(|(version, name, path)| {
+        quote! {
+            ::syewreal::migrations::Migration {
+                version: #version,
+                name: #name,
+                statements: include_str!(#path),
+            }
+        }
+    });
+    Ok(quote! {
+        ::syewreal::migrations::Migrations::new(vec![#(#migrations),*])
+    })
+}
+
+/// Add `#[surreal(table = "item", schema = "schema.surql")]` to check the remote fields against the
+/// `DEFINE TABLE`/`DEFINE FIELD` statements of a schema file, the path is relative to the crate root
+///
+/// With a `table` the struct gets a `query()` that starts a typed `syewreal::query::SelectBuilder`
+#[proc_macro_derive(SurrealProps, attributes(local, fallback, id, surreal))]
 pub fn derive_surreal_props(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
+    surreal_props(input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+fn surreal_props(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let surreal = SurrealAttr::from_attrs(&input.attrs)?;
 
     if let Data::Struct(data) = input.data {
         let name = input.ident;
 
-        let fields: Vec<_> = data.fields.into_iter().collect();
+        let fields: Vec<_> = match data.fields {
+            Fields::Named(fields) => fields.named.into_iter().collect(),
+            Fields::Unit => Vec::new(),
+            fields => {
+                return Err(Error::new_spanned(
+                    fields,
+                    "SurrealProps needs named fields\nhelp: the names of the fields are the names of the columns",
+                ))
+            }
+        };
 
         let (local_data, rest) = extract_attr(fields, "local");
 
@@ -60,29 +270,70 @@ pub fn derive_surreal_props(input: TokenStream) -> TokenStream {
         let local_with_state_name = create_ident(&name, "LocalWithState");
         
         // Get the 'id' field used for keying lists
-        let (id, rest) = find_optional_field(rest, "id");
+        let (id, rest) = find_optional_field(rest, "id")?;
 
         let id_getter = id.clone().map(|field| {
             let id_ident = field.ident;
 
             quote! {
-                impl syewreal::props::id::HasID for #name {
-                    fn id(&self) -> syewreal::props::id::ID {
+                impl ::syewreal::props::id::HasID for #name {
+                    fn id(&self) -> ::syewreal::props::id::ID {
                         self.#id_ident.as_ref().unwrap().clone()
                     }
                 }
                 
-                impl syewreal::props::id::HasID for #remote_name {
-                    fn id(&self) -> syewreal::props::id::ID {
+                impl ::syewreal::props::id::HasID for #remote_name {
+                    fn id(&self) -> ::syewreal::props::id::ID {
                         self.#id_ident.as_ref().unwrap().clone()
                     }
                 }
             }
         });
 
-        let (fallback, rest) = extract_optional_field(rest, "fallback");
+        let (fallback, rest) = extract_optional_field(rest, "fallback")?;
         let fallback_ident = fallback.as_ref().map(|field| field.ident.clone());
 
+        // rebuild whenever the schema changes
+        let schema_dependency = match &surreal.schema {
+            Some(schema) => {
+                let table = surreal.table.as_ref().ok_or_else(|| {
+                    Error::new_spanned(
+                        schema,
+                        "A schema can only be checked against a table\nhelp: add table = \"...\" to #[surreal(...)]",
+                    )
+                })?;
+                let (schema, path) = Schema::load(schema)?;
+                schema.check(table, &rest)?;
+                let path = path.to_string_lossy().into_owned();
+                Some(quote! {
+                    const _: &str = include_str!(#path);
+                })
+            }
+            None => None,
+        };
+
+        // `Name::query()` starts a typed `SelectBuilder` over the remote fields of the table
+        let fields_name = create_ident(&name, "Fields");
+        let query_builder = surreal.table.as_ref().map(|table| {
+            let field_idents = get_idents(&rest);
+            let field_names = field_idents.iter().map(|ident| ident.unraw().to_string());
+            let field_types = rest.iter().map(|field| &field.ty);
+            let vis = &input.vis;
+            quote! {
+                #vis struct #fields_name {
+                    #(pub #field_idents: ::syewreal::query::Field<#field_types>,)*
+                }
+
+                impl #name {
+                    #vis fn query() -> ::syewreal::query::SelectBuilder<#fields_name> {
+                        ::syewreal::query::SelectBuilder::new(#table, #fields_name {
+                            #(#field_idents: ::syewreal::query::Field::new(#field_names),)*
+                        })
+                    }
+                }
+            }
+        });
+
         let fallback_getter = fallback_ident.clone().map(|field| {
 
             quote! {
@@ -125,7 +376,8 @@ pub fn derive_surreal_props(input: TokenStream) -> TokenStream {
         let expanded = quote! {
 
 
-            #[derive(serde::Deserialize, serde::Serialize, Clone, PartialEq)]
+            #[derive(::syewreal::serde::Deserialize, ::syewreal::serde::Serialize, Clone, PartialEq)]
+            #[serde(crate = "::syewreal::serde")]
             struct #remote_name {
                 #(#remote_data,)*
                 #(#attr_idents: String,)*
@@ -135,9 +387,9 @@ pub fn derive_surreal_props(input: TokenStream) -> TokenStream {
             #[derive(Clone, yew::Properties, PartialEq)]
             struct #local_name {
                 #(#local_data,)*
-                selector: syewreal::props::selector::Selector,
+                selector: ::syewreal::props::selector::Selector,
                 #[prop_or_default]
-                parameters: syewreal::props::selector::Parameters,
+                parameters: ::syewreal::props::selector::Parameters,
                 #[prop_or_default]
                 filter: Option<yew::Callback<#name, bool>>,
                 #fallback
@@ -147,15 +399,15 @@ pub fn derive_surreal_props(input: TokenStream) -> TokenStream {
             struct #local_with_state_name {
                 #(#local_data,)*
                 #[prop_or_default]
-                parameters: syewreal::props::selector::Parameters,
+                parameters: ::syewreal::props::selector::Parameters,
                 #[prop_or_default]
                 filter: Option<yew::Callback<#name, bool>>,
-                state: syewreal::hooks::QueryState<#remote_name>,
+                state: ::syewreal::hooks::QueryState<#remote_name>,
                 #fallback
             }
 
 
-            impl syewreal::SurrealProps for #name {
+            impl ::syewreal::SurrealProps for #name {
                 type Remote = #remote_name;
                 type Local = #local_name;
                 type LocalWithState = #local_with_state_name;
@@ -187,9 +439,13 @@ pub fn derive_surreal_props(input: TokenStream) -> TokenStream {
             }
 
             #id_getter
+
+            #schema_dependency
+
+            #query_builder
             
-            impl syewreal::props::surreal_props::PropsNoState<#name, #remote_name, #local_with_state_name> for #local_name {
-                fn with_state(&self, state: syewreal::hooks::QueryState<#remote_name>) -> #local_with_state_name {
+            impl ::syewreal::props::surreal_props::PropsNoState<#name, #remote_name, #local_with_state_name> for #local_name {
+                fn with_state(&self, state: ::syewreal::hooks::QueryState<#remote_name>) -> #local_with_state_name {
                     #local_with_state_name {
                         #(#local_idents : self.#local_idents.clone(),)*
                         parameters: self.parameters.clone(),
@@ -199,17 +455,17 @@ pub fn derive_surreal_props(input: TokenStream) -> TokenStream {
                     }
                 }
 
-                fn get_selector(&self) -> syewreal::props::selector::Selector {
+                fn get_selector(&self) -> ::syewreal::props::selector::Selector {
                     self.selector.clone()
                 }
             }
             
-            impl syewreal::props::surreal_props::PropsWithState<#name, #remote_name> for #local_with_state_name {
-                fn get_state(&self) -> syewreal::hooks::QueryState<#remote_name> {
+            impl ::syewreal::props::surreal_props::PropsWithState<#name, #remote_name> for #local_with_state_name {
+                fn get_state(&self) -> ::syewreal::hooks::QueryState<#remote_name> {
                     self.state.clone()
                 }
 
-                fn get_parameters(&self) -> syewreal::props::selector::Parameters {
+                fn get_parameters(&self) -> ::syewreal::props::selector::Parameters {
                     self.parameters.clone()
                 }
 
@@ -221,13 +477,21 @@ pub fn derive_surreal_props(input: TokenStream) -> TokenStream {
             }
         };
 
-        // Hand the output tokens back to the compiler.
-        proc_macro::TokenStream::from(expanded)
+        Ok(expanded)
     } else {
-        proc_macro::TokenStream::default()
+        Err(Error::new_spanned(&input.ident, "SurrealProps can only be derived for structs"))
     }
 }
 
+/// Implement `syewreal::schema::SurrealSchema` from the fields of a struct, needs `#[surreal(table = "...")]`
+///
+/// `#[local]` and `#[fallback]` fields are skipped, add `schemafull` to the attribute for a `SCHEMAFULL` table
+#[proc_macro_derive(SurrealSchema, attributes(local, fallback, id, surreal))]
+pub fn derive_surreal_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive_schema(input).unwrap_or_else(Error::into_compile_error).into()
+}
+
 fn create_ident(ident: &Ident, suffix: &str) -> Ident {
     Ident::new(&(ident.to_string() + &suffix.to_owned()), Span::call_site()).into()
 }
@@ -245,24 +509,45 @@ fn extract_by_type(fields: Vec<Field>, field_type: &str) -> (Vec<Field>, Vec<Fie
         .partition(|x| x.ty == syn::parse_str(field_type).expect("is valid type"))
 }
 
-fn find_optional_field(fields: Vec<Field>, attr_name: &str) -> (Option<Field>, Vec<Field>) {
+fn find_optional_field(fields: Vec<Field>, attr_name: &str) -> syn::Result<(Option<Field>, Vec<Field>)> {
     let (req, rest) = extract_attr(fields, attr_name);
+    only_one(&req, attr_name)?;
 
-    if req.len() > 1 {
-        panic!("Only one field may be marked as '{}'.", attr_name)
-    }
-
-    (req.first().cloned(), [req, rest].concat())
+    Ok((req.first().cloned(), [req, rest].concat()))
 }
 
-fn extract_optional_field(fields: Vec<Field>, attr_name: &str) -> (Option<Field>, Vec<Field>) {
+fn extract_optional_field(fields: Vec<Field>, attr_name: &str) -> syn::Result<(Option<Field>, Vec<Field>)> {
     let (req, rest) = extract_attr(fields, attr_name);
+    only_one(&req, attr_name)?;
 
-    if req.len() > 1 {
-        panic!("Only one field may be marked as '{}'.", attr_name)
-    }
+    Ok((req.first().cloned(), rest))
+}
 
-    (req.first().cloned(), rest)
+/// Point at every field after the first one that is marked with `attr_name`
+fn only_one(fields: &[Field], attr_name: &str) -> syn::Result<()> {
+    let first = match fields.first().and_then(|field| field.ident.as_ref()) {
+        Some(first) => first,
+        None => return Ok(()),
+    };
+    let error = fields[1..]
+        .iter()
+        .map(|field| {
+            Error::new_spanned(
+                field.ident.as_ref().unwrap_or(first),
+                format!(
+                    "Only one field may be marked as #[{}]\nhelp: `{}` is marked already, remove #[{}] from one of them",
+                    attr_name, first, attr_name
+                ),
+            )
+        })
+        .reduce(|mut all, error| {
+            all.combine(error);
+            all
+        });
+    match error {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
 }
 
 /// split off all fields with a certain attr and strip that attr out of them
@@ -0,0 +1,306 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use surrealdb::sql::{parse, statements::DefineStatement, Part, Statement};
+use syn::{
+    ext::IdentExt, Attribute, Data, DeriveInput, Error, Field, GenericArgument, Lit, LitStr, Meta, NestedMeta, PathArguments,
+    Type,
+};
+
+/// The arguments of `#[surreal(table = "...", schema = "...", schemafull)]`
+#[derive(Default)]
+pub struct SurrealAttr {
+    pub table: Option<LitStr>,
+    pub schema: Option<LitStr>,
+    pub schemafull: bool,
+}
+
+const SURREAL_HELP: &str = "help: the arguments are table = \"...\", schema = \"...\" and schemafull";
+
+impl SurrealAttr {
+    pub fn from_attrs(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut result = Self::default();
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident("surreal")) {
+            let list = match attr.parse_meta()? {
+                Meta::List(list) => list,
+                meta => return Err(Error::new_spanned(meta, format!("Expected #[surreal(...)]\n{}", SURREAL_HELP))),
+            };
+            for nested in list.nested {
+                match &nested {
+                    NestedMeta::Meta(Meta::NameValue(pair)) => match (&pair.lit, pair.path.get_ident()) {
+                        (Lit::Str(value), Some(key)) if key == "table" => result.table = Some(value.clone()),
+                        (Lit::Str(value), Some(key)) if key == "schema" => result.schema = Some(value.clone()),
+                        _ => return Err(Error::new_spanned(nested, format!("Unknown argument\n{}", SURREAL_HELP))),
+                    },
+                    NestedMeta::Meta(Meta::Path(path)) if path.is_ident("schemafull") => result.schemafull = true,
+                    _ => return Err(Error::new_spanned(nested, format!("Unknown argument\n{}", SURREAL_HELP))),
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// The tables and fields defined in a schema file
+pub struct Schema {
+    /// the SurrealQL type of each field by table, `None` if the field has no `TYPE`
+    tables: HashMap<String, HashMap<String, Option<String>>>,
+}
+
+impl Schema {
+    /// Read a schema relative to the crate that uses the macro
+    pub fn load(schema: &LitStr) -> syn::Result<(Self, PathBuf)> {
+        let path = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default()).join(schema.value());
+        let raw = std::fs::read_to_string(&path).map_err(|error| {
+            Error::new_spanned(
+                schema,
+                format!("Could not read the schema {}: {}\nhelp: the path is relative to the crate root", path.display(), error),
+            )
+        })?;
+        let statements = parse(&raw)
+            .map_err(|error| Error::new_spanned(schema, format!("Syntax error in {}: {}", path.display(), error)))?;
+
+        let mut tables: HashMap<String, HashMap<String, Option<String>>> = HashMap::new();
+        for statement in statements.iter() {
+            match statement {
+                Statement::Define(DefineStatement::Table(table)) => {
+                    tables.entry(table.name.0.clone()).or_default();
+                }
+                Statement::Define(DefineStatement::Field(field)) => {
+                    let fields = tables.entry(field.what.0.clone()).or_default();
+                    match field.name.0.as_slice() {
+                        [Part::Field(name)] => {
+                            fields.insert(name.0.clone(), field.kind.as_ref().map(ToString::to_string));
+                        }
+                        // `img.url` or `tags[*]` describe what is inside a field, the field itself may hold anything
+                        [Part::Field(name), ..] => {
+                            fields.entry(name.0.clone()).or_insert(None);
+                        }
+                        _ => (),
+                    }
+                }
+                _ => (),
+            }
+        }
+        Ok((Self { tables }, path))
+    }
+
+    /// Fail if a field of the struct is not defined on `table` or can't hold the declared type
+    pub fn check(&self, table: &LitStr, fields: &[Field]) -> syn::Result<()> {
+        let defined = self.tables.get(&table.value()).ok_or_else(|| {
+            Error::new_spanned(
+                table,
+                format!(
+                    "The table '{}' is not defined in the schema\nhelp: add `DEFINE TABLE {};` to the schema",
+                    table.value(),
+                    table.value()
+                ),
+            )
+        })?;
+        let mut errors = Vec::new();
+        for field in fields {
+            let ident = field.ident.as_ref().expect("named field");
+            let name = ident.unraw().to_string();
+            // every record has an id
+            if name == "id" {
+                continue;
+            }
+            match defined.get(&name) {
+                None => errors.push(Error::new_spanned(
+                    ident,
+                    format!(
+                        "The field '{}' is not defined on the table '{}'\nhelp: add `DEFINE FIELD {} ON {};` to the schema or mark the field as #[local]",
+                        name,
+                        table.value(),
+                        name,
+                        table.value()
+                    ),
+                )),
+                Some(None) => (),
+                Some(Some(kind)) => {
+                    if let Err(reason) = compatible(&field.ty, kind) {
+                        errors.push(Error::new_spanned(
+                            &field.ty,
+                            format!(
+                                "The field '{}' can't be stored as {} on the table '{}'\nhelp: {}",
+                                name,
+                                kind,
+                                table.value(),
+                                reason
+                            ),
+                        ))
+                    }
+                }
+            }
+        }
+        // report every field at once
+        match errors.into_iter().reduce(|mut all, error| {
+            all.combine(error);
+            all
+        }) {
+            Some(errors) => Err(errors),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Split `name<inner>` or `name(inner)` into its parts
+fn split_kind(kind: &str) -> (&str, Option<&str>) {
+    match kind.find(|c| c == '<' || c == '(') {
+        Some(index) => (&kind[..index], Some(&kind[index + 1..kind.len() - 1])),
+        None => (kind, None),
+    }
+}
+
+/// The last segment of a type path and its first generic type argument, e.g. `Option` and `T` for `Option<T>`
+fn split_type(ty: &Type) -> Option<(String, Option<&Type>)> {
+    match ty {
+        Type::Path(path) => {
+            let segment = path.path.segments.last()?;
+            let inner = match &segment.arguments {
+                PathArguments::AngleBracketed(args) => args.args.iter().find_map(|arg| match arg {
+                    GenericArgument::Type(ty) => Some(ty),
+                    _ => None,
+                }),
+                _ => None,
+            };
+            Some((segment.ident.to_string(), inner))
+        }
+        Type::Reference(reference) => split_type(&reference.elem),
+        _ => None,
+    }
+}
+
+/// Whether a value of type `ty` can be stored in a field of the SurrealQL `kind`
+pub fn compatible(ty: &Type, kind: &str) -> Result<(), String> {
+    let (rust, inner) = match split_type(ty) {
+        Some(split) => split,
+        // tuples, arrays and the like are not checked
+        None => return Ok(()),
+    };
+    let (base, kind_inner) = split_kind(kind);
+
+    match (rust.as_str(), base) {
+        (_, "any") => Ok(()),
+        ("Option", "option") => match (inner, kind_inner) {
+            (Some(inner), Some(kind)) => compatible(inner, kind),
+            _ => Ok(()),
+        },
+        // a field without a value is read as `None`
+        ("Option", _) => match inner {
+            Some(inner) => compatible(inner, kind),
+            None => Ok(()),
+        },
+        (_, "option") => Err("the field may be NONE, use an Option".to_owned()),
+        ("Vec" | "VecDeque" | "HashSet" | "BTreeSet", "array" | "set") => match (inner, kind_inner) {
+            (Some(inner), Some(kind)) => compatible(inner, kind.split(',').next().unwrap_or(kind).trim()),
+            _ => Ok(()),
+        },
+        (rust, base) => match expected_kinds(rust) {
+            Some(kinds) if !kinds.contains(&base) => Err(format!("{} is stored as {}", rust, kinds.join(" or "))),
+            _ => Ok(()),
+        },
+    }
+}
+
+/// The SurrealQL types a Rust type can be stored as, `None` if the type is not known
+pub fn expected_kinds(rust: &str) -> Option<&'static [&'static str]> {
+    Some(match rust {
+        "bool" => &["bool"],
+        "String" | "str" | "AttrValue" | "Strand" => &["string"],
+        "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128" | "usize" => {
+            &["int", "number"]
+        }
+        "f32" | "f64" => &["float", "number", "decimal"],
+        "Decimal" => &["decimal", "number"],
+        "ID" | "ForeignKey" | "Thing" | "StaticChild" => &["record"],
+        "Vec" | "VecDeque" | "HashSet" | "BTreeSet" => &["array", "set"],
+        "HashMap" | "BTreeMap" | "Object" => &["object"],
+        "Datetime" | "DateTime" => &["datetime"],
+        "Duration" => &["duration"],
+        "Uuid" => &["uuid"],
+        "Geometry" => &["geometry"],
+        _ => return None,
+    })
+}
+
+/// `impl SurrealSchema` with a `DEFINE FIELD` for every field that is stored in the database
+pub fn derive_schema(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let surreal = SurrealAttr::from_attrs(&input.attrs)?;
+    let table = surreal
+        .table
+        .ok_or_else(|| {
+            Error::new_spanned(
+                &input.ident,
+                "#[derive(SurrealSchema)] needs the table of the records\nhelp: add #[surreal(table = \"...\")] to the struct",
+            )
+        })?
+        .value();
+    let fields = match input.data {
+        Data::Struct(data) => data.fields,
+        _ => return Err(Error::new_spanned(&input.ident, "SurrealSchema can only be derived for structs")),
+    };
+
+    let define_table = match surreal.schemafull {
+        true => format!("DEFINE TABLE {} SCHEMAFULL", table),
+        false => format!("DEFINE TABLE {}", table),
+    };
+    let define_fields = fields
+        .iter()
+        .filter(|field| {
+            !field
+                .attrs
+                .iter()
+                .any(|attr| attr.path.is_ident("local") || attr.path.is_ident("fallback"))
+        })
+        .filter_map(|field| {
+            let name = field.ident.as_ref()?.unraw().to_string();
+            // every record has an id
+            if name == "id" {
+                return None;
+            }
+            let define = format!("DEFINE FIELD {} ON {}", name, table);
+            Some(match kind_of(&field.ty) {
+                Some(kind) => quote!(format!("{} TYPE {}", #define, #kind)),
+                None => quote!(String::from(#define)),
+            })
+        });
+    let name = input.ident;
+
+    Ok(quote! {
+        impl ::syewreal::schema::SurrealSchema for #name {
+            const TABLE: &'static str = #table;
+
+            fn statements() -> Vec<String> {
+                vec![
+                    String::from(#define_table),
+                    #(#define_fields,)*
+                ]
+            }
+        }
+    })
+}
+
+/// The types `DEFINE FIELD ... TYPE` accepts without arguments, other kinds (`record`, `uuid`, `set`) are left untyped
+const DEFINABLE_KINDS: &[&str] = &["bool", "string", "int", "float", "decimal", "number", "object", "datetime", "duration"];
+
+/// An expression for the SurrealQL type of `ty`, `None` if the type is not known and the field is left untyped
+///
+/// `Option`s stay untyped so the field can be NONE, and arrays don't know the type of their items.
+/// The table of a `StaticChild<T>` is only known once `T` implements `SurrealSchema` itself
+fn kind_of(ty: &Type) -> Option<TokenStream2> {
+    let (rust, inner) = split_type(ty)?;
+    match rust.as_str() {
+        "Option" => None,
+        "Vec" | "VecDeque" | "HashSet" | "BTreeSet" => Some(quote!(String::from("array"))),
+        "StaticChild" => {
+            let inner = inner?;
+            Some(quote!(format!("record({})", <#inner as ::syewreal::schema::SurrealSchema>::TABLE)))
+        }
+        rust => {
+            let kind = expected_kinds(rust)?[0];
+            DEFINABLE_KINDS.contains(&kind).then(|| quote!(String::from(#kind)))
+        }
+    }
+}
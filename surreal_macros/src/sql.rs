@@ -0,0 +1,357 @@
+use proc_macro2::{Delimiter, LineColumn, Spacing, Span, TokenStream, TokenTree};
+use surrealdb::sql::{parse, Query};
+use syn::{
+    ext::IdentExt,
+    parse::{Parse, ParseStream},
+    Error, Ident, LitStr, Token,
+};
+
+/// Params that are set by the database itself and never bound by the caller
+const RESERVED_PARAMS: &[&str] = &[
+    "auth", "session", "scope", "token", "this", "parent", "value", "before", "after", "event", "input",
+];
+
+/// SurrealQL written as Rust tokens
+pub struct Sql {
+    pub raw: String,
+    /// The `$params` that have to be bound, in order of appearance
+    pub params: Vec<Ident>,
+    /// Where each token starts in `raw`, to point parse errors at the token they happened in
+    spans: Vec<(usize, Span)>,
+    /// Where the last written token ends and whether it is followed by a space if the locations are unknown
+    last: Option<(LineColumn, bool)>,
+}
+
+impl Sql {
+    /// Read either a string literal with its identifiers or SurrealQL written as Rust tokens
+    pub fn from_input(prefix: &str, item: proc_macro::TokenStream) -> syn::Result<Self> {
+        let item = TokenStream::from(item);
+        match item.clone().into_iter().next() {
+            Some(TokenTree::Literal(literal)) if syn::parse2::<LitStr>(TokenTree::Literal(literal.clone()).into()).is_ok() => {
+                Self::from_literal(prefix, syn::parse2(item)?)
+            }
+            _ => Ok(Self::from_tokens(prefix, item)),
+        }
+    }
+
+    /// Turn the tokens back into SurrealQL, `stringify!` would separate `$` from the name of the param
+    fn from_tokens(prefix: &str, item: TokenStream) -> Self {
+        let mut sql = Self {
+            raw: prefix.to_owned(),
+            params: Vec::new(),
+            spans: Vec::new(),
+            last: None,
+        };
+        sql.write(item);
+        sql
+    }
+
+    /// Tokens are separated like in the source (`img.url`, `item:one`, `time::now()`). If the compiler does not
+    /// report where a token is, every identifier, literal, group and lone punctuation is followed by a space, except
+    /// around `.` and `:` and after punctuation that is joined to the next one (`->`, `!=`)
+    fn write(&mut self, item: TokenStream) {
+        let mut tokens = item.into_iter().peekable();
+        while let Some(token) = tokens.next() {
+            match token {
+                TokenTree::Group(group) => {
+                    let (open, close) = match group.delimiter() {
+                        Delimiter::Parenthesis => ("(", ")"),
+                        Delimiter::Brace => ("{", "}"),
+                        Delimiter::Bracket => ("[", "]"),
+                        Delimiter::None => ("", ""),
+                    };
+                    self.separate(group.span_open(), false);
+                    self.spans.push((self.raw.len(), group.span()));
+                    self.raw.push_str(open);
+                    self.last = Some((group.span_open().end(), false));
+                    self.write(group.stream());
+                    self.separate(group.span_close(), true);
+                    self.raw.push_str(close);
+                    self.last = Some((group.span_close().end(), true));
+                }
+                TokenTree::Punct(punct) => {
+                    if punct.as_char() == '$' {
+                        if let Some(TokenTree::Ident(param)) = tokens.peek() {
+                            let name = param.to_string();
+                            let known = self.params.iter().any(|other| *other == name);
+                            if !known && !RESERVED_PARAMS.contains(&name.as_str()) {
+                                self.params.push(param.clone());
+                            }
+                        }
+                    }
+                    let tight = matches!(punct.as_char(), '.' | ':');
+                    self.separate(punct.span(), tight);
+                    self.spans.push((self.raw.len(), punct.span()));
+                    self.raw.push(punct.as_char());
+                    let space = punct.spacing() == Spacing::Alone && punct.as_char() != '$' && !tight;
+                    self.last = Some((punct.span().end(), space));
+                }
+                token => {
+                    self.separate(token.span(), false);
+                    self.spans.push((self.raw.len(), token.span()));
+                    self.raw.push_str(&token.to_string());
+                    self.last = Some((token.span().end(), true));
+                }
+            }
+        }
+    }
+
+    /// Write what was between the last token and the one at `span`, `tight` tokens never get a space before them if
+    /// the locations are unknown
+    fn separate(&mut self, span: Span, tight: bool) {
+        let (start, stop) = (span.start(), span.end());
+        let (end, space) = match self.last {
+            Some(last) => last,
+            None => return,
+        };
+        // line 0 or a token without width (`Span::call_site()`) means the location is unknown, tokens from other
+        // macros may come in any order
+        let known = start.line > 0
+            && end.line > 0
+            && (start.line, start.column) < (stop.line, stop.column)
+            && (start.line, start.column) >= (end.line, end.column);
+        let separator = match known {
+            true if start.line > end.line => "\n",
+            true if start.column > end.column => " ",
+            false if space && !tight => " ",
+            _ => "",
+        };
+        self.raw.push_str(separator);
+    }
+
+    /// Fill in the identifiers and collect the `$params` of a string literal, every error points at the literal
+    fn from_literal(prefix: &str, literal: Literal) -> syn::Result<Self> {
+        let span = literal.sql.span();
+        let mut raw = prefix.to_owned() + &literal.sql.value();
+
+        for (name, value) in &literal.identifiers {
+            let placeholder = format!("{{{}}}", name.unraw());
+            if !raw.contains(&placeholder) {
+                return Err(Error::new_spanned(
+                    name,
+                    format!("`{}` is not used in the query\nhelp: write {} where the identifier goes", name, placeholder),
+                ));
+            }
+            raw = raw.replace(&placeholder, &identifier(value)?);
+        }
+
+        let mut params = Vec::new();
+        for name in literal_params(&raw) {
+            let param = syn::parse_str::<Ident>(&name).map_err(|_| {
+                Error::new(
+                    span,
+                    format!(
+                        "${} can't be taken from a variable\nhelp: use `query` and bind ${} yourself",
+                        name, name
+                    ),
+                )
+            })?;
+            params.push(Ident::new(&param.to_string(), span));
+        }
+
+        Ok(Self {
+            raw,
+            params,
+            spans: vec![(0, span)],
+            last: None,
+        })
+    }
+
+    /// Parse the query, a syntax error points at the token it happened in
+    pub fn parse(&self) -> syn::Result<Query> {
+        parse(&self.raw).map_err(|error| {
+            let message = error.to_string();
+            let offset = error_offset(&self.raw, &message);
+            let near = offset
+                .map(|offset| self.raw[offset..].chars().take(30).collect::<String>())
+                .unwrap_or_default();
+            let span = offset
+                .and_then(|offset| self.spans.iter().rev().find(|(start, _)| *start <= offset))
+                .or_else(|| self.spans.first())
+                .map(|(_, span)| *span)
+                .unwrap_or_else(Span::call_site);
+            Error::new(
+                span,
+                format!(
+                    "Syntax error: {}\nhelp: the query was read as `{}`, the error is near `{}`",
+                    message,
+                    self.raw.trim(),
+                    near.trim()
+                ),
+            )
+        })
+    }
+}
+
+/// The byte offset of the error in `raw`, surrealdb reports it as "... line <line> at character <char> ..."
+fn error_offset(raw: &str, message: &str) -> Option<usize> {
+    let words = message.split_whitespace().collect::<Vec<_>>();
+    let number_after = |word: &str| {
+        let index = words.iter().position(|other| *other == word)?;
+        words.get(index + 1)?.trim_matches(|c: char| !c.is_ascii_digit()).parse::<usize>().ok()
+    };
+    let line = number_after("line")?;
+    let char = number_after("character")?;
+    let start = raw
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum::<usize>();
+    let offset = (start + char).min(raw.len());
+    // the position may point into a multi byte character
+    (0..=offset).rev().find(|offset| raw.is_char_boundary(*offset))
+}
+
+/// `"..."` optionally followed by `name = identifier` pairs
+struct Literal {
+    sql: LitStr,
+    identifiers: Vec<(Ident, Identifier)>,
+}
+
+/// A table or field name known at compile time, `table = item` or `table = "item"`
+enum Identifier {
+    Ident(Ident),
+    Str(LitStr),
+}
+
+impl Parse for Literal {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let sql = input.parse()?;
+        let mut identifiers = Vec::new();
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let name = Ident::parse_any(input)?;
+            input.parse::<Token![=]>()?;
+            let value = match input.peek(LitStr) {
+                true => Identifier::Str(input.parse()?),
+                false => Identifier::Ident(Ident::parse_any(input)?),
+            };
+            identifiers.push((name, value));
+        }
+        Ok(Self { sql, identifiers })
+    }
+}
+
+/// The identifier as SurrealQL, names that are not plain words are escaped with backticks
+fn identifier(value: &Identifier) -> syn::Result<String> {
+    let (name, span) = match value {
+        Identifier::Ident(ident) => (ident.unraw().to_string(), ident.span()),
+        Identifier::Str(literal) => (literal.value(), literal.span()),
+    };
+    if name.is_empty() || name.contains('`') {
+        return Err(Error::new(
+            span,
+            "Not a valid identifier\nhelp: identifiers can't be empty or contain a backtick, bind values as $params instead",
+        ));
+    }
+    Ok(match name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        true => name,
+        false => format!("`{}`", name),
+    })
+}
+
+/// The names of the `$params` in `raw` in order of appearance, without those in strings and the reserved ones
+fn literal_params(raw: &str) -> Vec<String> {
+    let mut params = Vec::new();
+    let mut quote = None;
+    let mut chars = raw.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(_), '\\') => {
+                chars.next();
+            }
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), _) => (),
+            (None, '\'' | '"' | '`') => quote = Some(c),
+            (None, '$') => {
+                let mut name = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    name.push(c);
+                }
+                if !name.is_empty() && !RESERVED_PARAMS.contains(&name.as_str()) && !params.contains(&name) {
+                    params.push(name);
+                }
+            }
+            (None, _) => (),
+        }
+    }
+    params
+}
+
+#[cfg(test)]
+mod tests {
+    use proc_macro2::{Span, TokenStream, TokenTree};
+
+    use super::Sql;
+
+    fn read(sql: &str) -> Sql {
+        Sql::from_tokens("", sql.parse().unwrap())
+    }
+
+    /// Like tokens passed on by another macro, they don't know where they are
+    fn read_without_locations(sql: &str) -> Sql {
+        fn forget(stream: TokenStream) -> TokenStream {
+            stream
+                .into_iter()
+                .map(|mut token| {
+                    if let TokenTree::Group(group) = &token {
+                        let mut inner = proc_macro2::Group::new(group.delimiter(), forget(group.stream()));
+                        inner.set_span(Span::call_site());
+                        token = TokenTree::Group(inner);
+                    }
+                    token.set_span(Span::call_site());
+                    token
+                })
+                .collect()
+        }
+        Sql::from_tokens("", forget(sql.parse().unwrap()))
+    }
+
+    #[test]
+    fn keeps_idioms_together() {
+        assert_eq!(read("SELECT img.url, tags[0] FROM item").raw, "SELECT img.url, tags[0] FROM item");
+        assert_eq!(read("SELECT * FROM item WHERE owner = $auth.id").raw, "SELECT * FROM item WHERE owner = $auth.id");
+    }
+
+    #[test]
+    fn keeps_record_ids_together() {
+        assert_eq!(read("SELECT * FROM item:one").raw, "SELECT * FROM item:one");
+        assert_eq!(read("UPDATE item:1 SET done = true").raw, "UPDATE item:1 SET done = true");
+    }
+
+    #[test]
+    fn keeps_functions_and_groups() {
+        assert_eq!(read("CREATE item SET created = time::now()").raw, "CREATE item SET created = time::now()");
+        assert_eq!(read("CREATE item CONTENT { done: false }").raw, "CREATE item CONTENT { done: false }");
+    }
+
+    #[test]
+    fn keeps_line_breaks() {
+        assert_eq!(read("SELECT *\nFROM item").raw, "SELECT *\nFROM item");
+    }
+
+    #[test]
+    fn collects_params_once_in_order() {
+        let sql = read("UPDATE $thing MERGE $data WHERE owner = $auth.id AND $thing != NONE");
+        let params = sql.params.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(params, ["thing", "data"]);
+        assert_eq!(sql.raw, "UPDATE $thing MERGE $data WHERE owner = $auth.id AND $thing != NONE");
+    }
+
+    #[test]
+    fn parses_what_it_wrote() {
+        assert!(read("SELECT img.url FROM item:one WHERE owner = $auth.id").parse().is_ok());
+        assert!(read("SELECT * FROM").parse().is_err());
+    }
+
+    #[test]
+    fn separates_tokens_without_locations() {
+        let sql = read_without_locations("SELECT img.url FROM item:one WHERE owner = $auth.id AND done != true");
+        assert_eq!(sql.raw, "SELECT img.url FROM item:one WHERE owner = $auth.id AND done != true");
+        let sql = read_without_locations("UPDATE item:one SET tags = [img.url]");
+        assert_eq!(sql.raw, "UPDATE item:one SET tags = [img.url]");
+    }
+}
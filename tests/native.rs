@@ -0,0 +1,223 @@
+//! Renders components on the server against an in-memory database, run with `cargo test --features ssr,kv-mem`
+use syewreal::{
+    components::SurrealContext,
+    config::ConnectionConfig,
+    error::SurrealError,
+    hooks::{use_prepared_query_state, use_surreal, use_surreal_connected, Fetch, QueryOptions},
+    migrations::{Migration, Migrations},
+    serde::Deserialize,
+    surrealdb::Surreal,
+    Client, SurrealProps,
+};
+use yew::{html::IntoPropValue, prelude::*, suspense::use_future, LocalServerRenderer};
+
+#[derive(SurrealProps, Properties, PartialEq, Clone)]
+struct ItemProps {
+    title: AttrValue,
+    done: bool,
+}
+
+#[derive(Properties)]
+struct DatabaseProps {
+    client: &'static Client,
+    children: Children,
+}
+
+impl PartialEq for DatabaseProps {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.client, other.client) && self.children == other.children
+    }
+}
+
+#[function_component(Database)]
+fn database(props: &DatabaseProps) -> Html {
+    let token = use_surreal_connected(props.client, ConnectionConfig::default());
+    html! {
+        <SurrealContext {token}>
+            { for props.children.iter() }
+        </SurrealContext>
+    }
+}
+
+#[function_component(Items)]
+fn items() -> HtmlResult {
+    let selector = "SELECT * FROM item ORDER BY title".into_prop_value();
+    let items = use_prepared_query_state::<ItemProps>(selector, QueryOptions::default())?;
+    Ok(html! {
+        <ul>
+            { for items.get_list().into_iter().map(|item| html! { <li>{ item.title }</li> }) }
+        </ul>
+    })
+}
+
+#[function_component(CreateItem)]
+fn create_item() -> HtmlResult {
+    let token = use_surreal();
+    let created = use_future(|| async move {
+        let item = ItemPropsRemote {
+            title: "Bread".to_owned(),
+            done: false,
+        };
+        let item: ItemPropsRemote = token.create("item".to_owned(), item).resolve().await?;
+        Ok::<_, SurrealError>(item.title)
+    })?;
+    Ok(match &*created {
+        Ok(title) => html! { <p>{ format!("created {}", title) }</p> },
+        Err(error) => html! { <p>{ error.to_string() }</p> },
+    })
+}
+
+#[derive(Properties, PartialEq)]
+struct MigrateProps {
+    migrations: Migrations,
+}
+
+#[function_component(Migrate)]
+fn migrate(props: &MigrateProps) -> HtmlResult {
+    let token = use_surreal();
+    let migrations = props.migrations.clone();
+    let migrated = use_future(|| async move { migrations.run(&token).await })?;
+    Ok(match &*migrated {
+        Ok(()) => html! { <p>{ "migrated" }</p> },
+        Err(error) => html! { <p>{ error.to_string() }</p> },
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "syewreal::serde")]
+struct Applied {
+    version: u64,
+}
+
+fn migrations(second: &'static str) -> Migrations {
+    Migrations::new(vec![
+        Migration {
+            version: 1,
+            name: "create_items",
+            statements: "DEFINE TABLE item SCHEMAFULL; DEFINE FIELD title ON item TYPE string; DEFINE FIELD done ON item TYPE bool;",
+        },
+        Migration {
+            version: 2,
+            name: "add_milk",
+            statements: second,
+        },
+    ])
+}
+
+async fn applied(client: &'static Client) -> Vec<u64> {
+    let mut response = client.query("SELECT version FROM migration ORDER BY version").await.unwrap();
+    let applied: Vec<Applied> = response.take(0).unwrap();
+    applied.into_iter().map(|applied| applied.version).collect()
+}
+
+async fn titles(client: &'static Client) -> Vec<String> {
+    let mut response = client.query("SELECT * FROM item ORDER BY title").await.unwrap();
+    let items: Vec<ItemPropsRemote> = response.take(0).unwrap();
+    items.into_iter().map(|item| item.title).collect()
+}
+
+async fn connect() -> &'static Client {
+    // the token needs a `'static` client, every test gets its own database
+    let client: &'static Client = Box::leak(Box::new(Surreal::init()));
+    client.connect("mem://").await.unwrap();
+    client.use_ns("test").use_db("test").await.unwrap();
+    client
+}
+
+async fn render(client: &'static Client, children: Html) -> String {
+    let props = DatabaseProps {
+        client,
+        children: Children::new(vec![children]),
+    };
+    LocalServerRenderer::<Database>::with_props(props).render().await
+}
+
+#[tokio::test]
+async fn prepared_queries_render_the_records() {
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async {
+            let client = connect().await;
+            client
+                .query("CREATE item:1 SET title = 'Milk', done = false; CREATE item:2 SET title = 'Eggs', done = true")
+                .await
+                .unwrap();
+
+            let html = render(client, html! { <Items/> }).await;
+            let eggs = html.find("<li>Eggs</li>").expect(&html);
+            let milk = html.find("<li>Milk</li>").expect(&html);
+            assert!(eggs < milk, "{}", html);
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn created_records_are_stored() {
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async {
+            let client = connect().await;
+
+            let html = render(client, html! { <CreateItem/> }).await;
+            assert!(html.contains("created Bread"), "{}", html);
+
+            let mut response = client.query("SELECT * FROM item").await.unwrap();
+            let items: Vec<ItemPropsRemote> = response.take(0).unwrap();
+            let titles = items.into_iter().map(|item| item.title).collect::<Vec<_>>();
+            assert_eq!(titles, vec!["Bread".to_owned()]);
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn migrations_are_applied_in_order() {
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async {
+            let client = connect().await;
+            let migrations = migrations("CREATE item:milk SET title = 'Milk', done = false;");
+
+            let html = render(client, html! { <Migrate {migrations}/> }).await;
+            assert!(html.contains("migrated"), "{}", html);
+            assert_eq!(applied(client).await, vec![1, 2]);
+            assert_eq!(titles(client).await, vec!["Milk".to_owned()]);
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn applied_migrations_are_not_run_again() {
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async {
+            let client = connect().await;
+            // creating the same record twice fails, so the second run must not apply anything
+            let migrations = migrations("CREATE item:milk SET title = 'Milk', done = false;");
+
+            render(client, html! { <Migrate migrations={migrations.clone()}/> }).await;
+            let html = render(client, html! { <Migrate {migrations}/> }).await;
+            assert!(html.contains("migrated"), "{}", html);
+            assert_eq!(applied(client).await, vec![1, 2]);
+            assert_eq!(titles(client).await, vec!["Milk".to_owned()]);
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn a_failing_migration_is_rolled_back() {
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async {
+            let client = connect().await;
+            // the second statement fails after the first one created the record
+            let migrations = migrations(
+                "CREATE item:milk SET title = 'Milk', done = false; CREATE item:milk SET title = 'Milk', done = true;",
+            );
+
+            let html = render(client, html! { <Migrate {migrations}/> }).await;
+            assert!(html.contains("Migration 2 failed"), "{}", html);
+            assert_eq!(applied(client).await, vec![1]);
+            assert!(titles(client).await.is_empty());
+        })
+        .await;
+}